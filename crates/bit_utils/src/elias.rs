@@ -15,6 +15,15 @@ pub fn read_gamma_elias(reader: &mut BitReader<impl Read>) -> io::Result<u64> {
     let mut word_len = 0usize;
     while last_bit == 0 {
         word_len += 1;
+
+        // code of the largest value, `u64::MAX - 1`, has 63 leading zeros
+        if word_len == u64::BITS as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Elias gamma code is longer than 64 bits",
+            ));
+        }
+
         last_bit = reader.read(1)?;
     }
 
@@ -125,7 +134,7 @@ mod test {
 
     #[test]
     fn test_read_0() {
-        let buffer = vec![0b10000000];
+        let buffer = [0b10000000];
         let mut reader = BitReader::new(&buffer[..]);
         let result = read_gamma_elias(&mut reader).unwrap();
         assert_eq!(result, 0);
@@ -133,7 +142,7 @@ mod test {
 
     #[test]
     fn test_read_1() {
-        let buffer = vec![0b01000000];
+        let buffer = [0b01000000];
         let mut reader = BitReader::new(&buffer[..]);
         let result = read_gamma_elias(&mut reader).unwrap();
         assert_eq!(result, 1);
@@ -141,7 +150,7 @@ mod test {
 
     #[test]
     fn test_read_2() {
-        let buffer = vec![0b01100000];
+        let buffer = [0b01100000];
         let mut reader = BitReader::new(&buffer[..]);
 
         let result = read_gamma_elias(&mut reader).unwrap();
@@ -150,7 +159,7 @@ mod test {
 
     #[test]
     fn test_read_3() {
-        let buffer = vec![0b00100000];
+        let buffer = [0b00100000];
         let mut reader = BitReader::new(&buffer[..]);
 
         let result = read_gamma_elias(&mut reader).unwrap();
@@ -159,7 +168,7 @@ mod test {
 
     #[test]
     fn test_read_4() {
-        let buffer = vec![0b00101000];
+        let buffer = [0b00101000];
         let mut reader = BitReader::new(&buffer[..]);
 
         let result = read_gamma_elias(&mut reader).unwrap();
@@ -168,7 +177,7 @@ mod test {
 
     #[test]
     fn test_read_5() {
-        let buffer = vec![0b00110000];
+        let buffer = [0b00110000];
         let mut reader = BitReader::new(&buffer[..]);
 
         let result = read_gamma_elias(&mut reader).unwrap();
@@ -177,7 +186,7 @@ mod test {
 
     #[test]
     fn test_read_6() {
-        let buffer = vec![0b00111000];
+        let buffer = [0b00111000];
         let mut reader = BitReader::new(&buffer[..]);
 
         let result = read_gamma_elias(&mut reader).unwrap();
@@ -186,7 +195,7 @@ mod test {
 
     #[test]
     fn test_read_14() {
-        let buffer = vec![0b00011110];
+        let buffer = [0b00011110];
         let mut reader = BitReader::new(&buffer[..]);
 
         let result = read_gamma_elias(&mut reader).unwrap();
@@ -195,10 +204,29 @@ mod test {
 
     #[test]
     fn test_read_16() {
-        let buffer = vec![0b00001000, 0b10000000];
+        let buffer = [0b00001000, 0b10000000];
         let mut reader = BitReader::new(&buffer[..]);
 
         let result = read_gamma_elias(&mut reader).unwrap();
         assert_eq!(result, 16);    
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_read_largest() {
+        let mut buffer = Vec::new();
+        let mut writer = WordWriter::new(&mut buffer);
+        write_gamma_elias(&mut writer, u64::MAX - 1).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        let mut reader = BitReader::new(&buffer[..]);
+        assert_eq!(read_gamma_elias(&mut reader).unwrap(), u64::MAX - 1);
+
+        let buffer = [0u8; 16];
+        let mut reader = BitReader::new(&buffer[..]);
+        assert_eq!(
+            read_gamma_elias(&mut reader).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+    }
+}
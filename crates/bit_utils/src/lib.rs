@@ -2,3 +2,4 @@ pub mod read;
 pub mod write;
 pub mod elias;
 pub mod zigzag;
+pub mod truncated;
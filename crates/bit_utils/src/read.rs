@@ -1,7 +1,7 @@
 use std::{
     convert::TryInto,
     io::{self, Read},
-    iter::repeat,
+    iter::repeat_n,
 };
 
 use crate::write::get_mask;
@@ -56,7 +56,7 @@ impl<R: Read> BitReader<R> {
                 buffer[0..len]
                     .iter()
                     .copied()
-                    .chain(repeat(0u8).take(8 - len))
+                    .chain(repeat_n(0u8, 8 - len))
                     .collect::<Vec<_>>()
                     .try_into()
                     .unwrap(),
@@ -76,6 +76,75 @@ impl<R: Read> BitReader<R> {
 
         Ok(result)
     }
+
+    /// Longest bit sequence [`BitReader::peek`] can look ahead.
+    pub const MAX_PEEK: usize = 56;
+
+    /// Returns next `count` bits without consuming them, along with the number
+    /// of bits actually available. Bits past the end of input are zeros.
+    pub fn peek(&mut self, count: usize) -> Result<(u64, usize), io::Error> {
+        assert!(count <= Self::MAX_PEEK, "unable to peek more than 56 bits");
+
+        while self.remainder_length < count {
+            let mut buffer = [0; 8];
+            let free_bytes = (u64::BITS as usize - self.remainder_length) / 8;
+            let len = self.reader.read(&mut buffer[..free_bytes])?;
+            if len == 0 {
+                break;
+            }
+
+            for byte in &buffer[..len] {
+                self.remainder |=
+                    (*byte as u64) << (u64::BITS as usize - 8 - self.remainder_length);
+                self.remainder_length += 8;
+            }
+        }
+
+        if count == 0 {
+            return Ok((0, 0));
+        }
+
+        Ok((
+            self.remainder >> (u64::BITS as usize - count),
+            count.min(self.remainder_length),
+        ))
+    }
+
+    /// Skips `count` bits, which must have been made available by [`BitReader::peek`].
+    pub fn consume(&mut self, count: usize) {
+        assert!(
+            count <= self.remainder_length,
+            "consuming bits that were not peeked"
+        );
+
+        self.remainder = self.remainder.checked_shl(count as u32).unwrap_or(0);
+        self.remainder_length -= count;
+    }
+}
+
+/// Reader counting bytes read through it.
+pub struct CountingReader<R: Read> {
+    reader: R,
+    count: u64,
+}
+
+impl<R: Read> CountingReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, count: 0 }
+    }
+
+    /// Number of bytes read so far.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.reader.read(buf)?;
+        self.count += len as u64;
+        Ok(len)
+    }
 }
 
 pub struct WordIter<R: Read> {
@@ -151,7 +220,7 @@ impl<R: Read> Iterator for WordIter<R> {
             self.words.reverse();
         }
 
-        return self.words.pop();
+        self.words.pop()
     }
 }
 
@@ -746,4 +815,22 @@ mod test {
             0b011100110110100101110010011100110110010100100001
         );
     }
+
+    #[test]
+    fn should_peek_and_consume_bits() {
+        let buffer = [0b10110011, 0b01101001, 0b11110000];
+        let mut reader = BitReader::new(&buffer[..]);
+
+        assert_eq!(reader.peek(3).unwrap(), (0b101, 3));
+        assert_eq!(reader.peek(12).unwrap(), (0b101100110110, 12));
+        reader.consume(2);
+        assert_eq!(reader.read(3).unwrap(), 0b110);
+        assert_eq!(reader.peek(9).unwrap(), (0b011011010, 9));
+        reader.consume(9);
+        assert_eq!(reader.peek(16).unwrap(), (0b0111110000000000, 10));
+        assert_eq!(reader.read(6).unwrap(), 0b011111);
+        assert_eq!(reader.peek(8).unwrap(), (0, 4));
+        reader.consume(4);
+        assert!(reader.read(1).is_err());
+    }
 }
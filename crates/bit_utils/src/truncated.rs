@@ -0,0 +1,87 @@
+use std::io::{self, Read, Write};
+
+use crate::{read::BitReader, write::WordWriter};
+
+/// Writes `value` out of `count` equally likely values. With `k` bits needed to
+/// address `count` rounded down to a power of two, the first values take `k`
+/// bits and the rest take `k + 1` bits.
+pub fn write_truncated_binary(
+    writer: &mut WordWriter<impl Write>,
+    value: u64,
+    count: u64,
+) -> io::Result<()> {
+    let (bits, short) = split(count);
+
+    if value < short {
+        writer.write((value, bits))
+    } else {
+        writer.write((value + short, bits + 1))
+    }
+}
+
+pub fn read_truncated_binary(reader: &mut BitReader<impl Read>, count: u64) -> io::Result<u64> {
+    let (bits, short) = split(count);
+    let value = reader.read(bits)?;
+
+    if value < short {
+        Ok(value)
+    } else {
+        Ok(((value << 1) | reader.read(1)?) - short)
+    }
+}
+
+/// Width of short codes and how many values get them.
+fn split(count: u64) -> (usize, u64) {
+    let bits = count.ilog2();
+    let short = (2 << bits) - count;
+
+    (bits as usize, short)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{read::BitReader, write::WordWriter};
+
+    use super::{read_truncated_binary, write_truncated_binary};
+
+    #[test]
+    fn should_shorten_first_values() {
+        let mut buffer = Vec::new();
+        let mut writer = WordWriter::new(&mut buffer);
+
+        // out of five values: 00, 01, 10, 110, 111
+        for value in 0..5 {
+            write_truncated_binary(&mut writer, value, 5).unwrap();
+        }
+        write_truncated_binary(&mut writer, 0, 1).unwrap();
+        drop(writer);
+
+        assert_eq!(buffer, vec![0b00011011, 0b01110000]);
+    }
+
+    #[test]
+    fn roundtrip_counts() {
+        let mut buffer = Vec::new();
+        let mut writer = WordWriter::new(&mut buffer);
+
+        for count in 1..100 {
+            for value in 0..count {
+                write_truncated_binary(&mut writer, value, count).unwrap();
+            }
+        }
+        write_truncated_binary(&mut writer, u32::MAX as u64, 1 << 40).unwrap();
+        drop(writer);
+
+        let mut reader = BitReader::new(&buffer[..]);
+
+        for count in 1..100 {
+            for value in 0..count {
+                assert_eq!(read_truncated_binary(&mut reader, count).unwrap(), value);
+            }
+        }
+        assert_eq!(
+            read_truncated_binary(&mut reader, 1 << 40).unwrap(),
+            u32::MAX as u64
+        );
+    }
+}
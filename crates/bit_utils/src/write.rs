@@ -51,13 +51,8 @@ impl<W: Write> WordWriter<W> {
             let bits_to_copy = bytes_to_write * 8 - self.buffer_length;
             let to_write = self.buffer.saturating_shl(bits_to_copy)
                 | (word >> (size - bits_to_copy)) & get_mask(bits_to_copy as u32);
-            self.writer.write_all(
-                &to_write
-                    .to_be_bytes()
-                    .into_iter()
-                    .skip(u64::BITS as usize / 8 - bytes_to_write)
-                    .collect::<Vec<_>>(),
-            )?;
+            self.writer
+                .write_all(&to_write.to_be_bytes()[u64::BITS as usize / 8 - bytes_to_write..])?;
             word &= get_mask(size as u32 - bits_to_copy as u32);
             size -= bits_to_copy;
             self.buffer = 0;
@@ -77,7 +72,7 @@ impl<W: Write> WordWriter<W> {
             return self.writer.flush();
         }
 
-        let byte_count = (self.buffer_length + 7) / 8;
+        let byte_count = self.buffer_length.div_ceil(8);
         let leftover = byte_count * 8 - self.buffer_length;
         self.buffer <<= leftover;
         self.writer.write_all(
@@ -88,6 +83,8 @@ impl<W: Write> WordWriter<W> {
                 .skip(u64::BITS as usize / 8 - byte_count)
                 .collect::<Vec<_>>(),
         )?;
+        self.buffer = 0;
+        self.buffer_length = 0;
 
         self.writer.flush()
     }
@@ -196,4 +193,16 @@ mod test {
 
         assert_eq!(buffer, vec![0b00000010, 0, 0, 0, 0, 0, 0, 0, 0b00000010]);
     }
+
+    #[test]
+    fn should_not_repeat_flushed_bits_on_drop() {
+        let mut buffer = Vec::new();
+
+        let mut writer = WordWriter::new(&mut buffer);
+        writer.write((0b101, 3)).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        assert_eq!(buffer, vec![0b10100000]);
+    }
 }
@@ -0,0 +1,102 @@
+use crate::{
+    error::{Error, Result},
+    suffix_array::suffix_array,
+};
+
+/// Burrows–Wheeler transform of `data` terminated by a virtual sentinel, smaller
+/// than every byte. Returns the last column of sorted rotations without the
+/// sentinel, along with the row it has been removed from.
+pub fn forward(data: &[u8]) -> (Vec<u8>, usize) {
+    let suffixes = suffix_array(data);
+    let mut last = Vec::with_capacity(data.len());
+    // sentinel alone is the smallest suffix, preceded by the last byte
+    last.extend(data.last());
+    let mut primary = 0;
+
+    for (row, &suffix) in suffixes.iter().enumerate() {
+        match suffix {
+            0 => primary = row + 1,
+            _ => last.push(data[suffix - 1]),
+        }
+    }
+
+    (last, primary)
+}
+
+/// Reverts [`forward`], the sentinel is put back to the `primary` row.
+pub fn inverse(last: &[u8], primary: usize) -> Result<Vec<u8>> {
+    let n = last.len();
+
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+
+    if !(1..=n).contains(&primary) {
+        return Err(Error::CorruptData("primary index is out of block"));
+    }
+
+    // first row of every byte in the first column, after the sentinel row
+    let mut first = [0usize; u8::MAX as usize + 2];
+    for &byte in last {
+        first[byte as usize + 1] += 1;
+    }
+    first[0] = 1;
+    for byte in 1..first.len() {
+        first[byte] += first[byte - 1];
+    }
+
+    // row of the rotation starting one byte earlier, for every row
+    let mut previous = vec![0; n + 1];
+    for row in (0..=n).filter(|&row| row != primary) {
+        let byte = last[row - (row > primary) as usize] as usize;
+        previous[row] = first[byte];
+        first[byte] += 1;
+    }
+
+    let mut data = vec![0; n];
+    let mut row = 0;
+    for position in (0..n).rev() {
+        if row == primary {
+            return Err(Error::CorruptData(
+                "not a Burrows-Wheeler transformed block",
+            ));
+        }
+
+        data[position] = last[row - (row > primary) as usize];
+        row = previous[row];
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{forward, inverse};
+
+    #[test]
+    fn should_transform_banana() {
+        let (last, primary) = forward(b"banana");
+
+        // rows: $, a$, ana$, anana$, banana$, na$, nana$
+        assert_eq!(last, b"annbaa");
+        assert_eq!(primary, 4);
+        assert_eq!(inverse(&last, primary).unwrap(), b"banana");
+    }
+
+    #[test]
+    fn roundtrip() {
+        for data in [
+            &b""[..],
+            b"a",
+            b"aaaaaaa",
+            b"abracadabra",
+            b"mississippi river",
+        ] {
+            let (last, primary) = forward(data);
+            assert_eq!(inverse(&last, primary).unwrap(), data);
+        }
+
+        assert!(inverse(b"abc", 0).is_err());
+        assert!(inverse(b"abc", 4).is_err());
+    }
+}
@@ -0,0 +1,40 @@
+use std::io;
+
+use thiserror::Error;
+
+use crate::MAX_BLOCK_SIZE;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Unexpected end of input")]
+    UnexpectedEof,
+    #[error("Block size {0} is not between 1 and {MAX_BLOCK_SIZE} bytes")]
+    InvalidBlockSize(u64),
+    #[error("Not valid block-sorted data: {0}")]
+    CorruptData(&'static str),
+    #[error(transparent)]
+    Huffman(huffman::Error),
+    #[error(transparent)]
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => Error::UnexpectedEof,
+            _ => Error::Io(err),
+        }
+    }
+}
+
+impl From<huffman::Error> for Error {
+    fn from(err: huffman::Error) -> Self {
+        match err {
+            huffman::Error::UnexpectedEof => Error::UnexpectedEof,
+            huffman::Error::Io(err) => Error::Io(err),
+            err => Error::Huffman(err),
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
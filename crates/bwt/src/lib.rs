@@ -0,0 +1,177 @@
+//! Block-sorting compressor in the manner of bzip2. Every block goes through
+//! [`burrows_wheeler::forward`], [`mtf::encode`] and [`rle::encode`], and the
+//! result is Huffman coded byte by byte.
+
+pub mod burrows_wheeler;
+pub mod error;
+pub mod mtf;
+pub mod rle;
+pub mod suffix_array;
+
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use bit_utils::{elias, read::BitReader, write::WordWriter};
+use huffman::{
+    histogram::Histogram,
+    tree::{DecodeTable, HuffmanTree},
+    TreeFormat,
+};
+
+pub use error::{Error, Result};
+
+/// Block size of bzip2 at its best compression level.
+pub const DEFAULT_BLOCK_SIZE: u32 = 900_000;
+
+/// Largest supported block, the decoder refuses to allocate more.
+pub const MAX_BLOCK_SIZE: u32 = 1 << 24;
+
+pub fn validate_block_size(block_size: u32) -> Result<()> {
+    if !(1..=MAX_BLOCK_SIZE).contains(&block_size) {
+        return Err(Error::InvalidBlockSize(block_size as u64));
+    }
+
+    Ok(())
+}
+
+/// Compresses `input` in blocks of at most `block_size` bytes. Every block is
+/// prefixed with its length and primary index of the transform, followed by
+/// a canonical Huffman tree and the coded bytes. Stream is terminated by an
+/// empty block.
+pub fn compress(block_size: u32, input: impl Read, output: impl Write) -> Result<()> {
+    validate_block_size(block_size)?;
+
+    let mut reader = BufReader::new(input);
+    let mut writer = WordWriter::new(BufWriter::new(output));
+    let options = huffman::Options {
+        tree_format: TreeFormat::Canonical,
+        ..huffman::Options::new(8)
+    };
+    let mut block = Vec::with_capacity(block_size as usize);
+
+    loop {
+        block.clear();
+        (&mut reader)
+            .take(block_size as u64)
+            .read_to_end(&mut block)?;
+
+        elias::write_gamma_elias(&mut writer, block.len() as u64)?;
+
+        if block.is_empty() {
+            break;
+        }
+
+        let (mut last, primary) = burrows_wheeler::forward(&block);
+        mtf::encode(&mut last);
+        let symbols = rle::encode(&last);
+
+        elias::write_gamma_elias(&mut writer, primary as u64)?;
+        elias::write_gamma_elias(&mut writer, symbols.len() as u64)?;
+
+        let histogram = Histogram::read(&mut &symbols[..], 8, None)?;
+        let tree = options.build_tree(histogram)?;
+        tree.write_canonical(&mut writer)?;
+
+        for &symbol in &symbols {
+            writer.write(tree.encode_word(symbol as u64)?)?;
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Decompresses output of [`compress`].
+pub fn decompress(input: impl Read, output: impl Write) -> Result<()> {
+    let mut reader = BitReader::new(BufReader::new(input));
+    let mut writer = BufWriter::new(output);
+
+    loop {
+        let block_size = elias::read_gamma_elias(&mut reader)?;
+
+        if block_size == 0 {
+            break;
+        }
+
+        if block_size > MAX_BLOCK_SIZE as u64 {
+            return Err(Error::InvalidBlockSize(block_size));
+        }
+
+        let primary = elias::read_gamma_elias(&mut reader)?;
+        let symbol_count = elias::read_gamma_elias(&mut reader)?;
+
+        // every byte takes at most two symbols
+        if symbol_count > block_size * 2 {
+            return Err(Error::CorruptData("too many symbols for block size"));
+        }
+
+        let tree = HuffmanTree::read_canonical(&mut reader)?;
+        let table = DecodeTable::new(&tree, DecodeTable::DEFAULT_INDEX_BITS);
+        let symbols = (0..symbol_count)
+            .map(|_| Ok(tree.decode_next_word_with(&table, &mut reader)? as u8))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut last = rle::decode(&symbols, block_size as usize)?;
+
+        if last.len() != block_size as usize {
+            return Err(Error::CorruptData("block size does not match its content"));
+        }
+
+        mtf::decode(&mut last);
+        let block = burrows_wheeler::inverse(&last, primary as usize)?;
+        writer.write_all(&block)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compress, decompress, Error};
+
+    fn roundtrip(block_size: u32, data: &[u8]) -> usize {
+        let mut compressed = Vec::new();
+        compress(block_size, data, &mut compressed).unwrap();
+        let mut output = Vec::new();
+        decompress(&compressed[..], &mut output).unwrap();
+
+        assert_eq!(output, data);
+
+        compressed.len()
+    }
+
+    #[test]
+    fn roundtrip_block_sizes() {
+        let data = b"the quick brown fox jumps over the lazy dog".repeat(10);
+
+        for block_size in [1, 2, 7, 64, 1000] {
+            roundtrip(block_size, &[]);
+            roundtrip(block_size, b"a");
+            roundtrip(block_size, &data);
+        }
+
+        let bytes = (0..=255u8).cycle().take(2000).collect::<Vec<_>>();
+        roundtrip(1000, &bytes);
+    }
+
+    #[test]
+    fn should_compress_repetitive_input() {
+        let data = b"abracadabra ".repeat(1000);
+
+        assert!(roundtrip(super::DEFAULT_BLOCK_SIZE, &data) < 100);
+    }
+
+    #[test]
+    fn should_reject_invalid_block_size() {
+        assert!(matches!(
+            compress(0, &b"abc"[..], Vec::new()),
+            Err(Error::InvalidBlockSize(0))
+        ));
+        assert!(matches!(
+            compress(super::MAX_BLOCK_SIZE + 1, &b"abc"[..], Vec::new()),
+            Err(Error::InvalidBlockSize(_))
+        ));
+    }
+}
@@ -0,0 +1,44 @@
+/// Move-to-front transform: every byte is replaced by its position in a list
+/// of all bytes, then moved to the front of it. Runs of equal bytes, common
+/// after [`crate::burrows_wheeler::forward`], become runs of zeros.
+pub fn encode(data: &mut [u8]) {
+    let mut order = initial_order();
+
+    for byte in data.iter_mut() {
+        let position = order.iter().position(|&b| b == *byte).unwrap();
+        order.copy_within(0..position, 1);
+        order[0] = *byte;
+        *byte = position as u8;
+    }
+}
+
+/// Reverts [`encode`].
+pub fn decode(data: &mut [u8]) {
+    let mut order = initial_order();
+
+    for byte in data.iter_mut() {
+        let position = *byte as usize;
+        *byte = order[position];
+        order.copy_within(0..position, 1);
+        order[0] = *byte;
+    }
+}
+
+fn initial_order() -> [u8; 256] {
+    std::array::from_fn(|byte| byte as u8)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, encode};
+
+    #[test]
+    fn should_move_bytes_to_front() {
+        let mut data = *b"bbbaaac";
+        encode(&mut data);
+        assert_eq!(data, [b'b', 0, 0, b'b', 0, 0, b'c']);
+
+        decode(&mut data);
+        assert_eq!(&data, b"bbbaaac");
+    }
+}
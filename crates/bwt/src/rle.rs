@@ -0,0 +1,114 @@
+//! Zero run-length coding of move-to-front output, in the manner of bzip2.
+//! A run of zeros is written as its length in bijective base two, least
+//! significant digit first, with [`RUN_A`] as digit one and [`RUN_B`] as digit
+//! two. Other values are shifted up by one, the two largest ones do not fit
+//! into a byte then and are written as [`ESCAPE`] followed by `0` or `1`.
+
+use crate::error::{Error, Result};
+
+pub const RUN_A: u8 = 0;
+pub const RUN_B: u8 = 1;
+pub const ESCAPE: u8 = u8::MAX;
+
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut run = 0usize;
+
+    for &byte in data {
+        if byte == 0 {
+            run += 1;
+            continue;
+        }
+
+        write_run(&mut output, run);
+        run = 0;
+
+        match byte {
+            ..=253 => output.push(byte + 1),
+            _ => output.extend([ESCAPE, byte - 254]),
+        }
+    }
+
+    write_run(&mut output, run);
+
+    output
+}
+
+fn write_run(output: &mut Vec<u8>, mut run: usize) {
+    while run > 0 {
+        // digits are one and two, so the run has to be decremented first
+        run -= 1;
+        output.push(if run & 1 == 0 { RUN_A } else { RUN_B });
+        run >>= 1;
+    }
+}
+
+/// Reverts [`encode`], output longer than `max_length` is an error.
+pub fn decode(data: &[u8], max_length: usize) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut run = 0usize;
+    let mut digit = 1usize;
+    let mut bytes = data.iter();
+
+    while let Some(&byte) = bytes.next() {
+        if byte == RUN_A || byte == RUN_B {
+            run = digit
+                .checked_mul((byte - RUN_A + 1) as usize)
+                .and_then(|value| value.checked_add(run))
+                .ok_or(Error::CorruptData("zero run is too long"))?;
+            digit = digit.saturating_mul(2);
+            continue;
+        }
+
+        if output.len() + run >= max_length {
+            return Err(Error::CorruptData("run-length coded block is too long"));
+        }
+
+        output.resize(output.len() + run, 0);
+        run = 0;
+        digit = 1;
+
+        output.push(match byte {
+            ESCAPE => match bytes.next() {
+                Some(&escaped @ 0..=1) => escaped + 254,
+                _ => return Err(Error::CorruptData("invalid escaped byte")),
+            },
+            _ => byte - 1,
+        });
+    }
+
+    if output.len() + run > max_length {
+        return Err(Error::CorruptData("run-length coded block is too long"));
+    }
+
+    output.resize(output.len() + run, 0);
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, encode, ESCAPE, RUN_A, RUN_B};
+
+    #[test]
+    fn should_encode_zero_runs() {
+        let data = [0, 0, 0, 0, 0, 7, 0, 254, 255];
+        let encoded = encode(&data);
+
+        // five is one plus two times two
+        assert_eq!(encoded, [RUN_A, RUN_B, 8, RUN_A, ESCAPE, 0, ESCAPE, 1]);
+        assert_eq!(decode(&encoded, data.len()).unwrap(), data);
+        assert!(decode(&encoded, data.len() - 1).is_err());
+    }
+
+    #[test]
+    fn roundtrip_runs() {
+        for length in 0..70 {
+            let data = [&vec![0; length][..], &[3], &vec![0; length / 3][..]].concat();
+            assert_eq!(decode(&encode(&data), data.len()).unwrap(), data);
+        }
+
+        assert!(decode(&[ESCAPE], 8).is_err());
+        assert!(decode(&[ESCAPE, 2], 8).is_err());
+    }
+}
@@ -0,0 +1,72 @@
+/// Sorts all suffixes of `data` by prefix doubling: suffixes ordered by their
+/// first `k` bytes are ordered by `2k` bytes with two passes of counting sort.
+/// A suffix which is a prefix of another one is the smaller of them.
+pub fn suffix_array(data: &[u8]) -> Vec<usize> {
+    let n = data.len();
+    // rank zero stands for the end of data, below every byte
+    let mut rank = data
+        .iter()
+        .map(|&byte| byte as usize + 1)
+        .collect::<Vec<_>>();
+    let mut suffixes = (0..n).collect::<Vec<_>>();
+    suffixes.sort_unstable_by_key(|&i| data[i]);
+
+    let mut by_second = Vec::with_capacity(n);
+    let mut next_rank = vec![0; n];
+    let mut counts = vec![0; n.max(u8::MAX as usize + 1) + 1];
+    let mut k = 1;
+
+    while k < n {
+        // suffixes shorter than `k` have nothing after the first half,
+        // the rest follow in order of their second half
+        by_second.clear();
+        by_second.extend(n - k..n);
+        by_second.extend(suffixes.iter().filter(|&&i| i >= k).map(|&i| i - k));
+
+        counts.fill(0);
+        for &i in &by_second {
+            counts[rank[i]] += 1;
+        }
+        for r in 1..counts.len() {
+            counts[r] += counts[r - 1];
+        }
+        for &i in by_second.iter().rev() {
+            counts[rank[i]] -= 1;
+            suffixes[counts[rank[i]]] = i;
+        }
+
+        let key = |i: usize| (rank[i], rank.get(i + k).copied().unwrap_or(0));
+        next_rank[suffixes[0]] = 1;
+        for pair in suffixes.windows(2) {
+            let step = (key(pair[0]) != key(pair[1])) as usize;
+            next_rank[pair[1]] = next_rank[pair[0]] + step;
+        }
+
+        std::mem::swap(&mut rank, &mut next_rank);
+
+        if rank[suffixes[n - 1]] == n {
+            break;
+        }
+
+        k *= 2;
+    }
+
+    suffixes
+}
+
+#[cfg(test)]
+mod test {
+    use super::suffix_array;
+
+    #[test]
+    fn should_sort_suffixes() {
+        assert_eq!(suffix_array(b"banana"), [5, 3, 1, 0, 4, 2]);
+        assert_eq!(suffix_array(b"aaaa"), [3, 2, 1, 0]);
+        assert_eq!(suffix_array(b""), [] as [usize; 0]);
+
+        let data = b"mississippi, abracadabra and the quick brown fox";
+        let mut expected = (0..data.len()).collect::<Vec<_>>();
+        expected.sort_by_key(|&i| &data[i..]);
+        assert_eq!(suffix_array(data), expected);
+    }
+}
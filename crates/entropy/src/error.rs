@@ -0,0 +1,41 @@
+use std::io;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Unexpected end of input")]
+    UnexpectedEof,
+    /// total of a frequency table, or size of a tANS table, is out of range
+    #[error("Table size of {0} bits is not supported")]
+    InvalidTableSize(u8),
+    #[error("Histogram can not be normalized to given total")]
+    InvalidHistogram,
+    #[error("Not valid entropy coded data: {0}")]
+    CorruptData(&'static str),
+    #[error(transparent)]
+    Huffman(huffman::Error),
+    #[error(transparent)]
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => Error::UnexpectedEof,
+            _ => Error::Io(err),
+        }
+    }
+}
+
+impl From<huffman::Error> for Error {
+    fn from(err: huffman::Error) -> Self {
+        match err {
+            huffman::Error::UnexpectedEof => Error::UnexpectedEof,
+            huffman::Error::Io(err) => Error::Io(err),
+            err => Error::Huffman(err),
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
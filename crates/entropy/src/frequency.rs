@@ -0,0 +1,230 @@
+use std::io::{Read, Write};
+
+use bit_utils::{elias, read::BitReader, write::WordWriter};
+use huffman::histogram::Histogram;
+
+use crate::error::{Error, Result};
+
+/// Word frequencies scaled to sum up to a power of two.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrequencyTable {
+    word_size: u8,
+    total_bits: u8,
+    freq: Vec<u32>,
+    /// sum of frequencies of all smaller words, total at the end
+    cumulative: Vec<u32>,
+}
+
+impl FrequencyTable {
+    /// Largest supported total, `1 << MAX_TOTAL_BITS`.
+    pub const MAX_TOTAL_BITS: u8 = 24;
+
+    /// Scales `histogram` to sum up to `1 << total_bits`. Every present word keeps
+    /// a frequency of at least one, rounding error is settled on the most
+    /// frequent words.
+    pub fn normalize(histogram: &Histogram, total_bits: u8) -> Result<Self> {
+        let word_size = histogram.get_word_size();
+        let counts = histogram.get_freq();
+        let sum = counts.iter().sum::<u64>();
+        let present = counts.iter().filter(|&&count| count > 0).count();
+
+        if total_bits > Self::MAX_TOTAL_BITS {
+            return Err(Error::InvalidTableSize(total_bits));
+        }
+
+        if present == 0 || present > 1 << total_bits {
+            return Err(Error::InvalidHistogram);
+        }
+
+        let total = 1u64 << total_bits;
+        let mut freq = counts
+            .iter()
+            .map(|&count| match count {
+                0 => 0,
+                _ => ((count as u128 * total as u128 / sum as u128) as u32).max(1),
+            })
+            .collect::<Vec<_>>();
+
+        let mut words = (0..freq.len())
+            .filter(|&word| freq[word] > 0)
+            .collect::<Vec<_>>();
+        words.sort_by_key(|&word| std::cmp::Reverse(counts[word]));
+
+        let scaled_sum = freq.iter().map(|&f| f as u64).sum::<u64>();
+        if scaled_sum < total {
+            freq[words[0]] += (total - scaled_sum) as u32;
+        } else {
+            let mut excess = scaled_sum - total;
+            for &word in &words {
+                let taken = excess.min(freq[word] as u64 - 1);
+                freq[word] -= taken as u32;
+                excess -= taken;
+            }
+        }
+
+        Ok(Self::from_freq(word_size, total_bits, freq))
+    }
+
+    fn from_freq(word_size: u8, total_bits: u8, freq: Vec<u32>) -> Self {
+        let mut cumulative = Vec::with_capacity(freq.len() + 1);
+        cumulative.push(0);
+        for &f in &freq {
+            cumulative.push(cumulative.last().unwrap() + f);
+        }
+
+        Self {
+            word_size,
+            total_bits,
+            freq,
+            cumulative,
+        }
+    }
+
+    pub fn get_word_size(&self) -> u8 {
+        self.word_size
+    }
+
+    pub fn get_total_bits(&self) -> u8 {
+        self.total_bits
+    }
+
+    pub fn get_freq(&self) -> &[u32] {
+        &self.freq
+    }
+
+    /// Cumulative frequency and frequency of `word`.
+    pub fn interval(&self, word: usize) -> (u32, u32) {
+        (self.cumulative[word], self.freq[word])
+    }
+
+    /// Word whose interval contains `target`.
+    pub fn word(&self, target: u32) -> usize {
+        self.cumulative
+            .partition_point(|&cumulative| cumulative <= target)
+            - 1
+    }
+
+    /// Writes frequencies of present words only, in the same way as canonical
+    /// Huffman trees: gamma coded count of absent words before every present
+    /// one, followed by its gamma coded frequency.
+    pub fn write(&self, writer: &mut WordWriter<impl Write>) -> Result<()> {
+        writer.write(((self.word_size - 2) as u64, 4))?;
+        writer.write((self.total_bits as u64, 5))?;
+
+        let mut absent = 0;
+        for &f in &self.freq {
+            if f == 0 {
+                absent += 1;
+                continue;
+            }
+
+            elias::write_gamma_elias(writer, absent)?;
+            elias::write_gamma_elias(writer, f as u64 - 1)?;
+            absent = 0;
+        }
+
+        if absent > 0 {
+            elias::write_gamma_elias(writer, absent)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read(reader: &mut BitReader<impl Read>) -> Result<Self> {
+        let word_size = reader.read(4)? as u8 + 2;
+        let total_bits = reader.read(5)? as u8;
+
+        if total_bits > Self::MAX_TOTAL_BITS {
+            return Err(Error::InvalidTableSize(total_bits));
+        }
+
+        let total = 1u64 << total_bits;
+        let mut freq = vec![0u32; 1 << word_size];
+        let mut sum = 0u64;
+        let mut word = 0;
+        while word < freq.len() {
+            let absent = elias::read_gamma_elias(reader)?;
+
+            if absent > (freq.len() - word) as u64 {
+                return Err(Error::CorruptData("frequencies do not match word size"));
+            }
+
+            word += absent as usize;
+
+            if word == freq.len() {
+                break;
+            }
+
+            let f = elias::read_gamma_elias(reader)? + 1;
+            sum += f;
+
+            if sum > total {
+                return Err(Error::CorruptData("frequencies exceed table total"));
+            }
+
+            freq[word] = f as u32;
+            word += 1;
+        }
+
+        if sum != total {
+            return Err(Error::CorruptData(
+                "frequencies do not sum up to table total",
+            ));
+        }
+
+        Ok(Self::from_freq(word_size, total_bits, freq))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use bit_utils::{read::BitReader, write::WordWriter};
+    use huffman::histogram::Histogram;
+
+    use super::FrequencyTable;
+    use crate::error::Error;
+
+    #[test]
+    fn should_normalize_histogram() {
+        let histogram: Histogram = vec![1000, 0, 1, 0, 3, 0, 0, 12].try_into().unwrap();
+        let table = FrequencyTable::normalize(&histogram, 6).unwrap();
+
+        assert_eq!(table.get_freq(), &[61, 0, 1, 0, 1, 0, 0, 1]);
+        assert_eq!(table.interval(7), (63, 1));
+        assert_eq!(table.word(0), 0);
+        assert_eq!(table.word(61), 2);
+        assert_eq!(table.word(62), 4);
+        assert_eq!(table.word(63), 7);
+
+        assert!(matches!(
+            FrequencyTable::normalize(&histogram, 1),
+            Err(Error::InvalidHistogram)
+        ));
+        assert!(matches!(
+            FrequencyTable::normalize(&histogram, 25),
+            Err(Error::InvalidTableSize(25))
+        ));
+        let empty: Histogram = vec![0, 0, 0, 0].try_into().unwrap();
+        assert!(matches!(
+            FrequencyTable::normalize(&empty, 8),
+            Err(Error::InvalidHistogram)
+        ));
+    }
+
+    #[test]
+    fn frequency_table_serialization_roundtrip() {
+        let histogram: Histogram = vec![0, 5, 9, 0, 0, 0, 1, 0].try_into().unwrap();
+        let table = FrequencyTable::normalize(&histogram, 12).unwrap();
+
+        let mut buffer = Vec::new();
+        {
+            let mut writer = WordWriter::new(&mut buffer);
+            table.write(&mut writer).unwrap();
+        }
+
+        let mut reader = BitReader::new(&buffer[..]);
+        assert_eq!(FrequencyTable::read(&mut reader).unwrap(), table);
+    }
+}
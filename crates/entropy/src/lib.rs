@@ -0,0 +1,9 @@
+pub mod error;
+pub mod frequency;
+pub mod model;
+pub mod range;
+pub mod rans;
+pub mod tans;
+
+pub use error::{Error, Result};
+pub use frequency::FrequencyTable;
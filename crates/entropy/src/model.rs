@@ -0,0 +1,167 @@
+use crate::frequency::FrequencyTable;
+
+/// Probability model of words, given as integer frequencies.
+pub trait Model {
+    fn total(&self) -> u32;
+
+    /// Cumulative frequency and frequency of `word`.
+    fn interval(&self, word: usize) -> (u32, u32);
+
+    /// Word whose interval contains `target`.
+    fn word(&self, target: u32) -> usize;
+
+    /// Accounts for another occurrence of `word`, static models ignore it.
+    fn update(&mut self, _word: usize) {}
+}
+
+impl Model for FrequencyTable {
+    fn total(&self) -> u32 {
+        1 << self.get_total_bits()
+    }
+
+    fn interval(&self, word: usize) -> (u32, u32) {
+        FrequencyTable::interval(self, word)
+    }
+
+    fn word(&self, target: u32) -> usize {
+        FrequencyTable::word(self, target)
+    }
+}
+
+/// Model learning frequencies from the words seen so far. Every word starts
+/// with frequency one, frequencies are halved when their total grows too large.
+pub struct AdaptiveModel {
+    freq: Vec<u32>,
+    /// Fenwick tree over `freq`, indexed from one
+    tree: Vec<u32>,
+    total: u32,
+    limit: u32,
+}
+
+impl AdaptiveModel {
+    const INCREMENT: u32 = 32;
+
+    pub fn new(word_count: usize) -> Self {
+        let mut model = Self {
+            freq: vec![1; word_count],
+            tree: Vec::new(),
+            total: 0,
+            limit: (word_count as u32 * 4).max(1 << 16),
+        };
+
+        model.rebuild();
+
+        model
+    }
+
+    fn rebuild(&mut self) {
+        self.tree = vec![0; self.freq.len() + 1];
+
+        for index in 1..self.tree.len() {
+            self.tree[index] += self.freq[index - 1];
+
+            let parent = index + (index & index.wrapping_neg());
+            if parent < self.tree.len() {
+                self.tree[parent] += self.tree[index];
+            }
+        }
+
+        self.total = self.freq.iter().sum();
+    }
+}
+
+impl Model for AdaptiveModel {
+    fn total(&self) -> u32 {
+        self.total
+    }
+
+    fn interval(&self, word: usize) -> (u32, u32) {
+        let mut cumulative = 0;
+        let mut index = word;
+
+        while index > 0 {
+            cumulative += self.tree[index];
+            index &= index - 1;
+        }
+
+        (cumulative, self.freq[word])
+    }
+
+    fn word(&self, mut target: u32) -> usize {
+        let mut word = 0;
+        let mut step = (self.tree.len() - 1).next_power_of_two();
+
+        while step > 0 {
+            if word + step < self.tree.len() && self.tree[word + step] <= target {
+                word += step;
+                target -= self.tree[word];
+            }
+
+            step >>= 1;
+        }
+
+        word
+    }
+
+    fn update(&mut self, word: usize) {
+        self.freq[word] += Self::INCREMENT;
+        self.total += Self::INCREMENT;
+
+        if self.total > self.limit {
+            for f in self.freq.iter_mut() {
+                *f = f.div_ceil(2);
+            }
+
+            self.rebuild();
+            return;
+        }
+
+        let mut index = word + 1;
+        while index < self.tree.len() {
+            self.tree[index] += Self::INCREMENT;
+            index += index & index.wrapping_neg();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AdaptiveModel, Model};
+
+    #[test]
+    fn adaptive_model_intervals_match_words() {
+        let mut model = AdaptiveModel::new(7);
+
+        for word in [3, 3, 0, 6, 3, 5, 5, 1] {
+            model.update(word);
+        }
+
+        let mut cumulative = 0;
+        for word in 0..7 {
+            let (start, freq) = model.interval(word);
+            assert_eq!(start, cumulative);
+
+            for target in start..start + freq {
+                assert_eq!(model.word(target), word);
+            }
+
+            cumulative += freq;
+        }
+
+        assert_eq!(cumulative, model.total());
+        assert_eq!(model.interval(3), (67, 97));
+    }
+
+    #[test]
+    fn adaptive_model_should_rescale() {
+        let mut model = AdaptiveModel::new(2);
+
+        for _ in 0..5000 {
+            model.update(1);
+        }
+
+        assert!(model.total() <= 1 << 16);
+        assert_eq!(model.interval(0), (0, 1));
+        assert_eq!(model.word(1), 1);
+    }
+}
@@ -0,0 +1,343 @@
+//! Range coder with carry propagation, as in LZMA. Interval of every word is
+//! narrowed in proportion to its frequency, so a word costs close to
+//! `log2(total / freq)` bits, also below one bit for very likely words.
+
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+
+use bit_utils::{
+    read::{BitReader, CountingReader, ToWordIter},
+    write::WordWriter,
+};
+use huffman::histogram::Histogram;
+
+use crate::{
+    error::{Error, Result},
+    frequency::FrequencyTable,
+    model::{AdaptiveModel, Model},
+};
+
+/// range is kept above this value, so there are always at least 24 bits of precision
+const TOP: u32 = 1 << 24;
+
+pub struct RangeEncoder {
+    low: u64,
+    range: u32,
+    /// byte waiting for a possible carry
+    cache: u8,
+    /// number of pending bytes, the cache followed by `0xFF` bytes
+    cache_size: u64,
+}
+
+impl RangeEncoder {
+    pub fn new() -> Self {
+        Self {
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            cache_size: 1,
+        }
+    }
+
+    /// Narrows range to `[cumulative, cumulative + frequency)` out of `total`.
+    pub fn encode(
+        &mut self,
+        cumulative: u32,
+        frequency: u32,
+        total: u32,
+        writer: &mut WordWriter<impl Write>,
+    ) -> Result<()> {
+        let scale = self.range / total;
+        self.low += scale as u64 * cumulative as u64;
+        self.range = scale * frequency;
+
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low(writer)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn encode_word(
+        &mut self,
+        model: &impl Model,
+        word: usize,
+        writer: &mut WordWriter<impl Write>,
+    ) -> Result<()> {
+        let (cumulative, frequency) = model.interval(word);
+        self.encode(cumulative, frequency, model.total(), writer)
+    }
+
+    fn shift_low(&mut self, writer: &mut WordWriter<impl Write>) -> Result<()> {
+        if self.low < 0xFF00_0000 || self.low > u32::MAX as u64 {
+            let carry = (self.low >> 32) as u8;
+            let mut byte = self.cache;
+
+            while self.cache_size > 0 {
+                writer.write((byte.wrapping_add(carry) as u64, 8))?;
+                byte = 0xFF;
+                self.cache_size -= 1;
+            }
+
+            self.cache = (self.low >> 24) as u8;
+        }
+
+        self.cache_size += 1;
+        self.low = (self.low << 8) & u32::MAX as u64;
+
+        Ok(())
+    }
+
+    /// Writes out the rest of the range, it takes five bytes.
+    pub fn finish(mut self, writer: &mut WordWriter<impl Write>) -> Result<()> {
+        for _ in 0..5 {
+            self.shift_low(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for RangeEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct RangeDecoder {
+    code: u32,
+    range: u32,
+    scale: u32,
+}
+
+impl RangeDecoder {
+    pub fn new(reader: &mut BitReader<impl Read>) -> Result<Self> {
+        let mut code = 0;
+        for _ in 0..5 {
+            code = (code << 8) | reader.read(8)? as u32;
+        }
+
+        Ok(Self {
+            code,
+            range: u32::MAX,
+            scale: 1,
+        })
+    }
+
+    /// Cumulative frequency out of `total` the next word falls to, has to be
+    /// followed by [`RangeDecoder::consume`] of the interval containing it.
+    pub fn target(&mut self, total: u32) -> u32 {
+        self.scale = self.range / total;
+        (self.code / self.scale).min(total - 1)
+    }
+
+    pub fn consume(
+        &mut self,
+        cumulative: u32,
+        frequency: u32,
+        reader: &mut BitReader<impl Read>,
+    ) -> Result<()> {
+        self.code -= self.scale * cumulative;
+        self.range = self.scale * frequency;
+
+        while self.range < TOP {
+            self.code = (self.code << 8) | reader.read(8)? as u32;
+            self.range <<= 8;
+        }
+
+        Ok(())
+    }
+
+    pub fn decode_word(
+        &mut self,
+        model: &impl Model,
+        reader: &mut BitReader<impl Read>,
+    ) -> Result<usize> {
+        let word = model.word(self.target(model.total()));
+        let (cumulative, frequency) = model.interval(word);
+        self.consume(cumulative, frequency, reader)?;
+
+        Ok(word)
+    }
+}
+
+/// Total of static frequency tables, leaving at least 32 bits of precision
+/// per word in the range after normalization.
+fn total_bits(word_size: u8) -> u8 {
+    (word_size + 2).max(16)
+}
+
+/// Compresses `input` with a static model built from its histogram, which is
+/// stored in front of the coded words.
+pub fn compress(word_size: u8, input: impl Read + Seek, output: impl Write) -> Result<()> {
+    let mut reader = BufReader::new(input);
+    let file_size = reader.seek(io::SeekFrom::End(0))?;
+    let mut writer = BufWriter::new(output);
+    writer.write_all(&file_size.to_be_bytes())?;
+
+    if file_size == 0 {
+        writer.flush()?;
+        return Ok(());
+    }
+
+    reader.rewind()?;
+    let histogram = Histogram::read(&mut reader, word_size, None)?;
+    let table = FrequencyTable::normalize(&histogram, total_bits(word_size))?;
+    reader.rewind()?;
+
+    let mut writer = WordWriter::new(writer);
+    table.write(&mut writer)?;
+
+    let mut encoder = RangeEncoder::new();
+    for word in reader.word_iter(word_size) {
+        encoder.encode_word(&table, word as usize, &mut writer)?;
+    }
+
+    encoder.finish(&mut writer)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Decompresses output of [`compress`].
+pub fn decompress(input: impl Read, output: impl Write) -> Result<()> {
+    let mut reader = BufReader::new(input);
+    let mut file_size = [0u8; 8];
+    reader.read_exact(&mut file_size)?;
+    let file_size = u64::from_be_bytes(file_size);
+
+    if file_size == 0 {
+        return Ok(());
+    }
+
+    let mut reader = BitReader::new(reader);
+    let table = FrequencyTable::read(&mut reader)?;
+    let word_size = table.get_word_size() as u64;
+    let mut decoder = RangeDecoder::new(&mut reader)?;
+
+    let mut writer = WordWriter::new(BufWriter::new(output));
+    let mut cursor = file_size
+        .checked_mul(8)
+        .ok_or(Error::CorruptData("file size is too large"))?;
+
+    while cursor > 0 {
+        let word = decoder.decode_word(&table, &mut reader)?;
+        writer.write((word as u64, word_size.min(cursor) as usize))?;
+        cursor = cursor.saturating_sub(word_size);
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Compresses `input` in a single pass with an adaptive model, nothing but
+/// the word size is stored. Stream is terminated by an extra end word.
+pub fn compress_adaptive(word_size: u8, input: impl Read, output: impl Write) -> Result<()> {
+    let mut reader = CountingReader::new(BufReader::new(input));
+    let mut writer = WordWriter::new(BufWriter::new(output));
+    writer.write(((word_size - 2) as u64, 4))?;
+
+    let end = 1usize << word_size;
+    let mut model = AdaptiveModel::new(end + 1);
+    let mut encoder = RangeEncoder::new();
+
+    for word in (&mut reader).word_iter(word_size) {
+        encoder.encode_word(&model, word as usize, &mut writer)?;
+        model.update(word as usize);
+    }
+
+    encoder.encode_word(&model, end, &mut writer)?;
+    // length of the last word, as input size is not known upfront
+    let tail = ((reader.count() * 8) % word_size as u64) as u32;
+    encoder.encode(tail, 1, u32::BITS, &mut writer)?;
+    encoder.finish(&mut writer)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Decompresses output of [`compress_adaptive`].
+pub fn decompress_adaptive(input: impl Read, output: impl Write) -> Result<()> {
+    let mut reader = BitReader::new(BufReader::new(input));
+    let mut writer = WordWriter::new(BufWriter::new(output));
+    let word_size = reader.read(4)? as u8 + 2;
+
+    let end = 1usize << word_size;
+    let mut model = AdaptiveModel::new(end + 1);
+    let mut decoder = RangeDecoder::new(&mut reader)?;
+    let mut pending = None;
+
+    loop {
+        let word = decoder.decode_word(&model, &mut reader)?;
+
+        if word == end {
+            break;
+        }
+
+        model.update(word);
+
+        if let Some(pending) = pending.replace(word) {
+            writer.write((pending as u64, word_size as usize))?;
+        }
+    }
+
+    let tail = decoder.target(u32::BITS);
+    decoder.consume(tail, 1, &mut reader)?;
+
+    if let Some(pending) = pending {
+        let length = if tail == 0 { word_size as u32 } else { tail };
+        writer.write((pending as u64, length as usize))?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::{compress, compress_adaptive, decompress, decompress_adaptive};
+
+    fn roundtrip(word_size: u8, data: &[u8]) -> (usize, usize) {
+        let mut compressed = Vec::new();
+        compress(word_size, Cursor::new(data), &mut compressed).unwrap();
+        let mut output = Vec::new();
+        decompress(&compressed[..], &mut output).unwrap();
+        assert_eq!(output, data);
+
+        let mut adaptive = Vec::new();
+        compress_adaptive(word_size, data, &mut adaptive).unwrap();
+        let mut output = Vec::new();
+        decompress_adaptive(&adaptive[..], &mut output).unwrap();
+        assert_eq!(output, data);
+
+        (compressed.len(), adaptive.len())
+    }
+
+    #[test]
+    fn roundtrip_word_sizes() {
+        let data = b"abracadabra, the quick brown fox jumps over the lazy dog".repeat(4);
+
+        for word_size in [2, 3, 7, 8, 11, 16, 17] {
+            roundtrip(word_size, &[]);
+            roundtrip(word_size, &[0xA5]);
+            roundtrip(word_size, &data);
+        }
+    }
+
+    #[test]
+    fn should_code_likely_words_below_one_bit() {
+        let data = (0..10000u32)
+            .map(|i| if i % 20 == 0 { b'b' } else { b'a' })
+            .collect::<Vec<_>>();
+
+        let (compressed, adaptive) = roundtrip(8, &data);
+
+        // Huffman coding needs at least one bit per byte
+        assert!(compressed * 8 < data.len() / 2);
+        assert!(adaptive * 8 < data.len() / 2);
+    }
+}
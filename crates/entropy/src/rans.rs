@@ -0,0 +1,327 @@
+//! Range variant of asymmetric numeral systems with byte-wise renormalization.
+//! Frequencies may change from word to word, which is used here for adaptive
+//! coding: word counts are normalized into a fresh table every now and then.
+
+use std::{
+    convert::TryFrom,
+    io::{BufReader, BufWriter, Read, Write},
+};
+
+use bit_utils::{
+    elias,
+    read::{BitReader, CountingReader, ToWordIter},
+    write::WordWriter,
+};
+use huffman::histogram::Histogram;
+
+use crate::{
+    error::{Error, Result},
+    frequency::FrequencyTable,
+};
+
+/// lower bound of the coder state, which is kept in `[LOW, LOW << 8)`
+const LOW: u32 = 1 << 23;
+
+/// Number of words coded from a single state, the encoder keeps them in memory.
+const BLOCK_SIZE: usize = 1 << 16;
+
+pub struct RansEncoder {
+    state: u32,
+    /// output in reverse order
+    bytes: Vec<u8>,
+}
+
+impl RansEncoder {
+    pub fn new() -> Self {
+        Self {
+            state: LOW,
+            bytes: Vec::new(),
+        }
+    }
+
+    /// Encodes word with interval `[cumulative, cumulative + frequency)` out of
+    /// `1 << total_bits`. Words have to be encoded in reverse order.
+    pub fn encode(&mut self, cumulative: u32, frequency: u32, total_bits: u8) {
+        let max_state = ((LOW >> total_bits) << 8) * frequency;
+
+        while self.state >= max_state {
+            self.bytes.push(self.state as u8);
+            self.state >>= 8;
+        }
+
+        self.state = ((self.state / frequency) << total_bits) + self.state % frequency + cumulative;
+    }
+
+    /// Returns coded bytes in decoding order, starting with the final state.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.bytes.extend(self.state.to_le_bytes());
+        self.bytes.reverse();
+        self.bytes
+    }
+}
+
+impl Default for RansEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct RansDecoder {
+    state: u32,
+}
+
+impl RansDecoder {
+    pub fn new(reader: &mut BitReader<impl Read>) -> Result<Self> {
+        Ok(Self {
+            state: reader.read(u32::BITS as usize)? as u32,
+        })
+    }
+
+    /// Cumulative frequency out of `1 << total_bits` the next word falls to, has
+    /// to be followed by [`RansDecoder::consume`] of the interval containing it.
+    pub fn target(&self, total_bits: u8) -> u32 {
+        self.state & ((1 << total_bits) - 1)
+    }
+
+    pub fn consume(
+        &mut self,
+        cumulative: u32,
+        frequency: u32,
+        total_bits: u8,
+        reader: &mut BitReader<impl Read>,
+    ) -> Result<()> {
+        // state of a damaged stream may be anything, so it is let to wrap
+        self.state = frequency
+            .wrapping_mul(self.state >> total_bits)
+            .wrapping_add(self.target(total_bits) - cumulative);
+
+        while self.state < LOW {
+            self.state = (self.state << 8) | reader.read(8)? as u32;
+        }
+
+        Ok(())
+    }
+
+    /// Whether the decoder returned to the initial state of the encoder,
+    /// which it does after the last word of a valid block.
+    pub fn is_finished(&self) -> bool {
+        self.state == LOW
+    }
+}
+
+/// Word counts, normalized into a frequency table after exponentially growing
+/// intervals, so the table follows the input closely at its start. As in
+/// [`crate::model::AdaptiveModel`], every word starts with count one and seen
+/// words outweigh it quickly.
+struct AdaptiveTable {
+    counts: Vec<u64>,
+    table: FrequencyTable,
+    seen: usize,
+    next_update: usize,
+    max_interval: usize,
+}
+
+impl AdaptiveTable {
+    const MIN_INTERVAL: usize = 1 << 4;
+    const INCREMENT: u64 = 32;
+
+    fn new(word_size: u8) -> Result<Self> {
+        let counts = vec![1; 1 << word_size];
+
+        Ok(Self {
+            table: Self::normalize(&counts, word_size)?,
+            counts,
+            seen: 0,
+            next_update: Self::MIN_INTERVAL,
+            max_interval: (1 << 16).max(1 << word_size),
+        })
+    }
+
+    fn normalize(counts: &[u64], word_size: u8) -> Result<FrequencyTable> {
+        let histogram = Histogram::try_from(counts.to_vec())?;
+
+        // every word is present, so the total has to be above the word count
+        FrequencyTable::normalize(&histogram, (word_size + 2).max(16))
+    }
+
+    fn update(&mut self, word: usize) -> Result<()> {
+        self.counts[word] += Self::INCREMENT;
+        self.seen += 1;
+
+        if self.seen == self.next_update {
+            self.table = Self::normalize(&self.counts, self.table.get_word_size())?;
+            self.next_update += self.seen.clamp(Self::MIN_INTERVAL, self.max_interval);
+        }
+
+        Ok(())
+    }
+}
+
+/// Compresses `input` in a single pass with adaptive rANS. Words are coded
+/// in blocks, each prefixed with gamma coded word count, an empty block ends
+/// the stream.
+pub fn compress_adaptive(word_size: u8, input: impl Read, output: impl Write) -> Result<()> {
+    let mut reader = CountingReader::new(BufReader::new(input));
+    let mut writer = WordWriter::new(BufWriter::new(output));
+    writer.write(((word_size - 2) as u64, 4))?;
+
+    let mut model = AdaptiveTable::new(word_size)?;
+    let mut words = (&mut reader).word_iter(word_size);
+    let mut intervals = Vec::with_capacity(BLOCK_SIZE);
+
+    loop {
+        // intervals have to be taken in input order, as the model changes
+        intervals.clear();
+        for word in words.by_ref().take(BLOCK_SIZE) {
+            intervals.push(model.table.interval(word as usize));
+            model.update(word as usize)?;
+        }
+
+        elias::write_gamma_elias(&mut writer, intervals.len() as u64)?;
+
+        if intervals.is_empty() {
+            break;
+        }
+
+        let total_bits = model.table.get_total_bits();
+        let mut encoder = RansEncoder::new();
+        for &(cumulative, frequency) in intervals.iter().rev() {
+            encoder.encode(cumulative, frequency, total_bits);
+        }
+
+        for byte in encoder.finish() {
+            writer.write((byte as u64, 8))?;
+        }
+    }
+
+    drop(words);
+    // length of the last word, as input size is not known upfront
+    writer.write(((reader.count() * 8) % word_size as u64, 5))?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Decompresses output of [`compress_adaptive`].
+pub fn decompress_adaptive(input: impl Read, output: impl Write) -> Result<()> {
+    let mut reader = BitReader::new(BufReader::new(input));
+    let mut writer = WordWriter::new(BufWriter::new(output));
+    let word_size = reader.read(4)? as u8 + 2;
+
+    let mut model = AdaptiveTable::new(word_size)?;
+    let mut pending = None;
+
+    loop {
+        let count = elias::read_gamma_elias(&mut reader)?;
+
+        if count == 0 {
+            break;
+        }
+
+        let total_bits = model.table.get_total_bits();
+        let mut decoder = RansDecoder::new(&mut reader)?;
+
+        for _ in 0..count {
+            let word = model.table.word(decoder.target(total_bits));
+            let (cumulative, frequency) = model.table.interval(word);
+            decoder.consume(cumulative, frequency, total_bits, &mut reader)?;
+            model.update(word)?;
+
+            if let Some(pending) = pending.replace(word) {
+                writer.write((pending as u64, word_size as usize))?;
+            }
+        }
+
+        if !decoder.is_finished() {
+            return Err(Error::CorruptData(
+                "rANS block does not end in initial state",
+            ));
+        }
+    }
+
+    let tail = reader.read(5)? as usize;
+
+    if let Some(pending) = pending {
+        writer.write((
+            pending as u64,
+            if tail == 0 { word_size as usize } else { tail },
+        ))?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use bit_utils::read::BitReader;
+
+    use super::{compress_adaptive, decompress_adaptive, RansDecoder, RansEncoder};
+
+    fn roundtrip(word_size: u8, data: &[u8]) -> usize {
+        let mut compressed = Vec::new();
+        compress_adaptive(word_size, data, &mut compressed).unwrap();
+        let mut output = Vec::new();
+        decompress_adaptive(&compressed[..], &mut output).unwrap();
+
+        assert_eq!(output, data);
+
+        compressed.len()
+    }
+
+    #[test]
+    fn coder_roundtrip() {
+        // intervals out of 16
+        let intervals = [(0, 9), (9, 4), (13, 2), (15, 1)];
+        let words = [0, 0, 1, 3, 0, 2, 0, 0, 1, 0, 3, 3, 2];
+
+        let mut encoder = RansEncoder::new();
+        for &word in words.iter().rev() {
+            let (cumulative, frequency) = intervals[word];
+            encoder.encode(cumulative, frequency, 4);
+        }
+        let bytes = encoder.finish();
+
+        let mut reader = BitReader::new(&bytes[..]);
+        let mut decoder = RansDecoder::new(&mut reader).unwrap();
+        for word in words {
+            let target = decoder.target(4);
+            assert!((intervals[word].0..intervals[word].0 + intervals[word].1).contains(&target));
+            decoder
+                .consume(intervals[word].0, intervals[word].1, 4, &mut reader)
+                .unwrap();
+        }
+
+        assert!(decoder.is_finished());
+    }
+
+    #[test]
+    fn roundtrip_word_sizes() {
+        let data = b"abracadabra, the quick brown fox jumps over the lazy dog".repeat(4);
+
+        for word_size in [2, 3, 7, 8, 11, 16, 17] {
+            roundtrip(word_size, &[]);
+            roundtrip(word_size, &[0xA5]);
+            roundtrip(word_size, &data);
+        }
+    }
+
+    #[test]
+    fn roundtrip_multiple_blocks() {
+        let data = (0..40000u32)
+            .map(|i| (i * i % 251) as u8 & 0x0F)
+            .collect::<Vec<_>>();
+
+        roundtrip(2, &data);
+    }
+
+    #[test]
+    fn should_adapt_to_likely_words() {
+        let data = (0..10000u32)
+            .map(|i| if i % 20 == 0 { b'b' } else { b'a' })
+            .collect::<Vec<_>>();
+
+        assert!(roundtrip(8, &data) * 8 < data.len() / 2);
+    }
+}
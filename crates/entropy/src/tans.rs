@@ -0,0 +1,308 @@
+//! Table variant of asymmetric numeral systems. Coder state is an index into
+//! a table where every word owns as many slots as its normalized frequency, so
+//! both coding directions are a table lookup and a few raw bits per word.
+
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+
+use bit_utils::{
+    read::{BitReader, ToWordIter},
+    write::WordWriter,
+};
+use huffman::histogram::Histogram;
+
+use crate::{
+    error::{Error, Result},
+    frequency::FrequencyTable,
+};
+
+/// Number of words coded from a single state, the encoder keeps them in memory.
+const BLOCK_SIZE: usize = 1 << 16;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DecodeEntry {
+    word: u32,
+    bits: u8,
+    /// next state before adding the bits read
+    base: u32,
+}
+
+pub struct TansTable {
+    table_bits: u8,
+    decode: Vec<DecodeEntry>,
+    /// state following every (word, sub-state) pair, grouped by word
+    encode: Vec<u32>,
+    cumulative: Vec<u32>,
+    freq: Vec<u32>,
+}
+
+impl TansTable {
+    pub const MIN_TABLE_BITS: u8 = 5;
+    pub const MAX_TABLE_BITS: u8 = 20;
+
+    pub fn new(table: &FrequencyTable) -> Result<Self> {
+        let table_bits = table.get_total_bits();
+
+        if !(Self::MIN_TABLE_BITS..=Self::MAX_TABLE_BITS).contains(&table_bits) {
+            return Err(Error::InvalidTableSize(table_bits));
+        }
+
+        let size = 1usize << table_bits;
+        let freq = table.get_freq().to_vec();
+
+        // spread slots of every word over the table, the step is odd and so
+        // visits every slot exactly once
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut spread = vec![0u32; size];
+        let mut position = 0;
+        for (word, &f) in freq.iter().enumerate() {
+            for _ in 0..f {
+                spread[position] = word as u32;
+                position = (position + step) & (size - 1);
+            }
+        }
+
+        let mut cumulative = Vec::with_capacity(freq.len());
+        let mut sum = 0;
+        for &f in &freq {
+            cumulative.push(sum);
+            sum += f;
+        }
+
+        let mut next = freq.clone();
+        let mut decode = vec![DecodeEntry::default(); size];
+        let mut encode = vec![0u32; size];
+
+        for (state, &word) in spread.iter().enumerate() {
+            let word = word as usize;
+            let sub_state = next[word];
+            next[word] += 1;
+
+            let bits = table_bits as u32 - sub_state.ilog2();
+            decode[state] = DecodeEntry {
+                word: word as u32,
+                bits: bits as u8,
+                base: (sub_state << bits) - size as u32,
+            };
+            encode[(cumulative[word] + sub_state - freq[word]) as usize] = (size + state) as u32;
+        }
+
+        Ok(Self {
+            table_bits,
+            decode,
+            encode,
+            cumulative,
+            freq,
+        })
+    }
+
+    /// Writes `words` as a single block: final encoder state followed by
+    /// the bits of every word, in decoding order.
+    pub fn encode_block(&self, words: &[u64], writer: &mut WordWriter<impl Write>) -> Result<()> {
+        let size = 1u32 << self.table_bits;
+        let mut state = size;
+        let mut chunks = Vec::with_capacity(words.len());
+
+        // states are a stack, so words are encoded from the last one
+        for &word in words.iter().rev() {
+            let word = word as usize;
+            let freq = self.freq[word];
+            let mut bits = state.ilog2() - freq.ilog2();
+            if state >> bits < freq {
+                bits -= 1;
+            }
+
+            chunks.push(((state & ((1 << bits) - 1)) as u64, bits as usize));
+            let sub_state = state >> bits;
+            state = self.encode[(self.cumulative[word] + sub_state - freq) as usize];
+        }
+
+        writer.write(((state - size) as u64, self.table_bits as usize))?;
+        for &chunk in chunks.iter().rev() {
+            writer.write(chunk)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a block of `count` words written by [`TansTable::encode_block`].
+    pub fn decode_block(
+        &self,
+        count: usize,
+        reader: &mut BitReader<impl Read>,
+        mut output: impl FnMut(u64) -> Result<()>,
+    ) -> Result<()> {
+        let mut state = reader.read(self.table_bits as usize)? as usize;
+
+        for _ in 0..count {
+            let entry = self.decode[state];
+            output(entry.word as u64)?;
+            state = (entry.base as u64 + reader.read(entry.bits as usize)?) as usize;
+        }
+
+        if state != 0 {
+            return Err(Error::CorruptData(
+                "tANS block does not end in initial state",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Table size, large enough for every word of the alphabet to fit in.
+fn table_bits(word_size: u8) -> u8 {
+    (word_size + 3).clamp(TansTable::MIN_TABLE_BITS, TansTable::MAX_TABLE_BITS)
+}
+
+/// Compresses `input` with a static frequency table built from its histogram,
+/// which is stored in front of the coded words.
+pub fn compress(word_size: u8, input: impl Read + Seek, output: impl Write) -> Result<()> {
+    let mut reader = BufReader::new(input);
+    let file_size = reader.seek(io::SeekFrom::End(0))?;
+    let mut writer = BufWriter::new(output);
+    writer.write_all(&file_size.to_be_bytes())?;
+
+    if file_size == 0 {
+        writer.flush()?;
+        return Ok(());
+    }
+
+    reader.rewind()?;
+    let histogram = Histogram::read(&mut reader, word_size, None)?;
+    let table = FrequencyTable::normalize(&histogram, table_bits(word_size))?;
+    let tans = TansTable::new(&table)?;
+    reader.rewind()?;
+
+    let mut writer = WordWriter::new(writer);
+    table.write(&mut writer)?;
+
+    let mut words = reader.word_iter(word_size);
+    let mut block = Vec::with_capacity(BLOCK_SIZE);
+
+    loop {
+        block.clear();
+        block.extend(words.by_ref().take(BLOCK_SIZE));
+
+        if block.is_empty() {
+            break;
+        }
+
+        tans.encode_block(&block, &mut writer)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Decompresses output of [`compress`].
+pub fn decompress(input: impl Read, output: impl Write) -> Result<()> {
+    let mut reader = BufReader::new(input);
+    let mut file_size = [0u8; 8];
+    reader.read_exact(&mut file_size)?;
+    let file_size = u64::from_be_bytes(file_size);
+
+    if file_size == 0 {
+        return Ok(());
+    }
+
+    let mut reader = BitReader::new(reader);
+    let table = FrequencyTable::read(&mut reader)?;
+    let word_size = table.get_word_size() as u64;
+    let tans = TansTable::new(&table)?;
+
+    let mut writer = WordWriter::new(BufWriter::new(output));
+    let mut cursor = file_size
+        .checked_mul(8)
+        .ok_or(Error::CorruptData("file size is too large"))?;
+
+    while cursor > 0 {
+        let count = cursor.div_ceil(word_size).min(BLOCK_SIZE as u64) as usize;
+
+        tans.decode_block(count, &mut reader, |word| {
+            writer.write((word, word_size.min(cursor) as usize))?;
+            cursor = cursor.saturating_sub(word_size);
+            Ok(())
+        })?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{convert::TryInto, io::Cursor};
+
+    use bit_utils::{read::BitReader, write::WordWriter};
+    use huffman::histogram::Histogram;
+
+    use super::{compress, decompress, TansTable};
+    use crate::frequency::FrequencyTable;
+
+    fn roundtrip(word_size: u8, data: &[u8]) -> usize {
+        let mut compressed = Vec::new();
+        compress(word_size, Cursor::new(data), &mut compressed).unwrap();
+        let mut output = Vec::new();
+        decompress(&compressed[..], &mut output).unwrap();
+
+        assert_eq!(output, data);
+
+        compressed.len()
+    }
+
+    #[test]
+    fn table_roundtrip() {
+        let histogram: Histogram = vec![7, 0, 1, 30, 2, 0, 0, 12].try_into().unwrap();
+        let table = FrequencyTable::normalize(&histogram, 5).unwrap();
+        let tans = TansTable::new(&table).unwrap();
+        let words = [3, 3, 7, 0, 2, 3, 4, 7, 7, 3, 0, 3];
+
+        let mut buffer = Vec::new();
+        {
+            let mut writer = WordWriter::new(&mut buffer);
+            tans.encode_block(&words, &mut writer).unwrap();
+        }
+
+        let mut decoded = Vec::new();
+        let mut reader = BitReader::new(&buffer[..]);
+        tans.decode_block(words.len(), &mut reader, |word| {
+            decoded.push(word);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(decoded, words);
+    }
+
+    #[test]
+    fn roundtrip_word_sizes() {
+        let data = b"abracadabra, the quick brown fox jumps over the lazy dog".repeat(4);
+
+        for word_size in [2, 3, 7, 8, 11, 16, 17] {
+            roundtrip(word_size, &[]);
+            roundtrip(word_size, &[0xA5]);
+            roundtrip(word_size, &data);
+        }
+    }
+
+    #[test]
+    fn roundtrip_multiple_blocks() {
+        let data = (0..40000u32)
+            .map(|i| (i * i % 251) as u8 & 0x0F)
+            .collect::<Vec<_>>();
+
+        roundtrip(2, &data);
+        roundtrip(4, &data);
+    }
+
+    #[test]
+    fn should_code_likely_words_below_one_bit() {
+        let data = (0..10000u32)
+            .map(|i| if i % 20 == 0 { b'b' } else { b'a' })
+            .collect::<Vec<_>>();
+
+        assert!(roundtrip(8, &data) * 8 < data.len() / 2);
+    }
+}
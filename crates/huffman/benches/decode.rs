@@ -0,0 +1,91 @@
+//! Decoding throughput of walking the tree bit by bit, against lookups in a
+//! [`DecodeTable`] resolving one word or several at once.
+
+use bit_utils::{read::BitReader, write::WordWriter};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use huffman::{
+    histogram::Histogram,
+    tree::{DecodeTable, HuffmanTree},
+};
+
+/// A megabyte of English-like text, words picked by a fixed pseudo-random
+/// sequence.
+fn sample() -> Vec<u8> {
+    let words = "the of and a to in is you that it he was for on are as with his they at be \
+        this have from or one had by word but not what all were compression Huffman entropy, \
+        decoder.\n"
+        .split(' ')
+        .collect::<Vec<_>>();
+    let mut state = 0x2545_F491u32;
+    let mut data = Vec::with_capacity(1 << 20);
+
+    while data.len() < 1 << 20 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        data.extend(words[state as usize % words.len()].as_bytes());
+        data.push(b' ');
+    }
+
+    data
+}
+
+fn decode(c: &mut Criterion) {
+    let data = sample();
+    let histogram = Histogram::read(&mut &data[..], 8, None).unwrap();
+    let tree = HuffmanTree::from(histogram);
+    let table = DecodeTable::new(&tree, DecodeTable::DEFAULT_INDEX_BITS);
+
+    let mut encoded = Vec::new();
+    {
+        let mut writer = WordWriter::new(&mut encoded);
+        for &byte in &data {
+            writer
+                .write(tree.encode_word(byte as u64).unwrap())
+                .unwrap();
+        }
+    }
+
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+
+    group.bench_function("tree walk", |b| {
+        b.iter(|| {
+            let mut reader = BitReader::new(&encoded[..]);
+            for _ in 0..data.len() {
+                black_box(tree.decode_next_word(&mut reader).unwrap());
+            }
+        })
+    });
+
+    group.bench_function("table, one word", |b| {
+        b.iter(|| {
+            let mut reader = BitReader::new(&encoded[..]);
+            for _ in 0..data.len() {
+                black_box(tree.decode_next_word_with(&table, &mut reader).unwrap());
+            }
+        })
+    });
+
+    group.bench_function("table, several words", |b| {
+        b.iter(|| {
+            let mut reader = BitReader::new(&encoded[..]);
+            let mut words = [0; DecodeTable::MAX_WORDS];
+            let mut remaining = data.len();
+
+            while remaining > 0 {
+                let limit = remaining.min(words.len());
+                let count = tree
+                    .decode_next_words_with(&table, &mut reader, &mut words[..limit])
+                    .unwrap();
+                black_box(&words[..count]);
+                remaining -= count;
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
@@ -0,0 +1,314 @@
+use bit_utils::{
+    read::{BitReader, CountingReader, ToWordIter},
+    write::WordWriter,
+};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+use crate::error::{Error, Result};
+
+const NONE: usize = usize::MAX;
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    weight: u64,
+    parent: usize,
+    left: usize,
+    right: usize,
+    word: u64,
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Self {
+            weight: 0,
+            parent: NONE,
+            left: NONE,
+            right: NONE,
+            word: 0,
+        }
+    }
+}
+
+/// Adaptive Huffman model (FGK algorithm). Encoder and decoder start from a tree
+/// containing only the "not yet transmitted" (NYT) node and update it after every
+/// word, so no tree is stored in the output.
+///
+/// Alphabet consists of all `word_size`-bit words plus an end-of-stream word.
+/// Words seen for the first time are sent as the NYT code followed by the raw
+/// word in `word_size + 1` bits.
+pub struct AdaptiveHuffman {
+    word_size: u8,
+    /// nodes indexed by their implicit number, root has the highest one
+    nodes: Vec<Node>,
+    leaves: Vec<usize>,
+    nyt: usize,
+}
+
+impl AdaptiveHuffman {
+    pub fn new(word_size: u8) -> Self {
+        let alphabet_size = (1usize << word_size) + 1;
+        let root = alphabet_size * 2;
+
+        Self {
+            word_size,
+            nodes: vec![Node::default(); root + 1],
+            leaves: vec![NONE; alphabet_size],
+            nyt: root,
+        }
+    }
+
+    pub fn get_word_size(&self) -> u8 {
+        self.word_size
+    }
+
+    fn root(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    fn end_word(&self) -> u64 {
+        1 << self.word_size
+    }
+
+    pub fn encode_word(
+        &mut self,
+        word: u64,
+        writer: &mut WordWriter<impl Write>,
+    ) -> io::Result<()> {
+        let leaf = self.leaves[word as usize];
+
+        if leaf == NONE {
+            self.write_path(self.nyt, writer)?;
+            writer.write((word, self.word_size as usize + 1))?;
+        } else {
+            self.write_path(leaf, writer)?;
+        }
+
+        self.update(word);
+
+        Ok(())
+    }
+
+    /// Writes end-of-stream word, after which [`AdaptiveHuffman::decode_next_word`] returns `None`.
+    pub fn encode_end(&mut self, writer: &mut WordWriter<impl Write>) -> io::Result<()> {
+        self.encode_word(self.end_word(), writer)
+    }
+
+    pub fn decode_next_word(&mut self, reader: &mut BitReader<impl Read>) -> Result<Option<u64>> {
+        let mut head = self.root();
+
+        while self.nodes[head].left != NONE {
+            head = if reader.read(1)? == 0 {
+                self.nodes[head].left
+            } else {
+                self.nodes[head].right
+            };
+        }
+
+        let word = if head == self.nyt {
+            let word = reader.read(self.word_size as usize + 1)?;
+
+            if word > self.end_word() || self.leaves[word as usize] != NONE {
+                return Err(Error::CorruptData("unexpected new adaptive Huffman word"));
+            }
+
+            word
+        } else {
+            self.nodes[head].word
+        };
+
+        self.update(word);
+
+        Ok((word != self.end_word()).then_some(word))
+    }
+
+    fn write_path(&self, mut node: usize, writer: &mut WordWriter<impl Write>) -> io::Result<()> {
+        let mut path = Vec::new();
+
+        while node != self.root() {
+            let parent = self.nodes[node].parent;
+            path.push(self.nodes[parent].right == node);
+            node = parent;
+        }
+
+        for chunk in path.rchunks(u64::BITS as usize) {
+            let code = chunk.iter().rev().fold(0, |a, i| (a << 1) | (*i as u64));
+            writer.write((code, chunk.len()))?;
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, word: u64) {
+        let mut node = self.leaves[word as usize];
+
+        if node == NONE {
+            // split NYT into new NYT (left) and leaf for the new word (right)
+            let parent = self.nyt;
+            let (nyt, leaf) = (parent - 2, parent - 1);
+
+            self.nodes[nyt] = Node {
+                parent,
+                ..Node::default()
+            };
+            self.nodes[leaf] = Node {
+                parent,
+                word,
+                ..Node::default()
+            };
+            self.nodes[parent].left = nyt;
+            self.nodes[parent].right = leaf;
+            self.leaves[word as usize] = leaf;
+            self.nyt = nyt;
+
+            node = leaf;
+        }
+
+        loop {
+            let leader = self.block_leader(node);
+
+            if leader != node && leader != self.nodes[node].parent {
+                self.swap(node, leader);
+                node = leader;
+            }
+
+            self.nodes[node].weight += 1;
+
+            if node == self.root() {
+                break;
+            }
+
+            node = self.nodes[node].parent;
+        }
+    }
+
+    /// Highest numbered node with the same weight as `node`. Weights never decrease
+    /// with node number (sibling property), so the block can be binary searched.
+    fn block_leader(&self, node: usize) -> usize {
+        let weight = self.nodes[node].weight;
+
+        node + self.nodes[node..].partition_point(|other| other.weight <= weight) - 1
+    }
+
+    /// Exchanges subtrees rooted at `a` and `b`, keeping their numbers.
+    fn swap(&mut self, a: usize, b: usize) {
+        let (parent_a, parent_b) = (self.nodes[a].parent, self.nodes[b].parent);
+        self.nodes.swap(a, b);
+        self.nodes[a].parent = parent_a;
+        self.nodes[b].parent = parent_b;
+
+        for node in [a, b] {
+            let Node {
+                left, right, word, ..
+            } = self.nodes[node];
+
+            if left == NONE {
+                self.leaves[word as usize] = node;
+            } else {
+                self.nodes[left].parent = node;
+                self.nodes[right].parent = node;
+            }
+        }
+    }
+}
+
+/// Compresses `input` in a single pass with adaptive Huffman coding.
+pub fn compress_adaptive(word_size: u8, input: impl Read, output: impl Write) -> Result<()> {
+    let mut reader = CountingReader::new(BufReader::new(input));
+    let mut writer = WordWriter::new(BufWriter::new(output));
+    writer.write(((word_size - 2) as u64, 4))?;
+
+    let mut model = AdaptiveHuffman::new(word_size);
+
+    for word in (&mut reader).word_iter(word_size) {
+        model.encode_word(word, &mut writer)?;
+    }
+
+    model.encode_end(&mut writer)?;
+    // length of the last word, as input size is not known upfront
+    writer.write(((reader.count() * 8) % word_size as u64, 5))?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Decompresses output of [`compress_adaptive`].
+pub fn decompress_adaptive(input: impl Read, output: impl Write) -> Result<()> {
+    let mut reader = BitReader::new(BufReader::new(input));
+    let mut writer = WordWriter::new(BufWriter::new(output));
+    let word_size = reader.read(4)? as u8 + 2;
+
+    let mut model = AdaptiveHuffman::new(word_size);
+    let mut pending = None;
+
+    while let Some(word) = model.decode_next_word(&mut reader)? {
+        if let Some(pending) = pending.replace(word) {
+            writer.write((pending, word_size as usize))?;
+        }
+    }
+
+    let tail = reader.read(5)? as usize;
+
+    if let Some(pending) = pending {
+        writer.write((pending, if tail == 0 { word_size as usize } else { tail }))?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use bit_utils::{read::BitReader, write::WordWriter};
+
+    use super::{compress_adaptive, decompress_adaptive, AdaptiveHuffman};
+
+    fn roundtrip(word_size: u8, data: &[u8]) {
+        let mut compressed = Vec::new();
+        compress_adaptive(word_size, data, &mut compressed).unwrap();
+        let mut output = Vec::new();
+        decompress_adaptive(&compressed[..], &mut output).unwrap();
+
+        assert_eq!(output, data);
+    }
+
+    #[test]
+    fn roundtrip_empty() {
+        roundtrip(8, &[]);
+    }
+
+    #[test]
+    fn roundtrip_word_sizes() {
+        let data = b"abracadabra, the quick brown fox jumps over the lazy dog".repeat(4);
+
+        for word_size in [2, 3, 7, 8, 11, 16] {
+            roundtrip(word_size, &data);
+        }
+    }
+
+    #[test]
+    fn should_shorten_codes_of_frequent_words() {
+        let mut buffer = Vec::new();
+        let mut model = AdaptiveHuffman::new(8);
+        {
+            let mut writer = WordWriter::new(&mut buffer);
+            // NYT path is empty for the first word, so it takes 9 raw bits
+            model.encode_word(b'a' as u64, &mut writer).unwrap();
+            // 'a' is a single leaf next to NYT now, so it is coded with 1 bit
+            model.encode_word(b'a' as u64, &mut writer).unwrap();
+            model.encode_word(b'a' as u64, &mut writer).unwrap();
+        }
+
+        assert_eq!(buffer, vec![0b00110000, 0b11100000]);
+
+        let mut reader = BitReader::new(&buffer[..]);
+        let mut model = AdaptiveHuffman::new(8);
+
+        for _ in 0..3 {
+            assert_eq!(
+                model.decode_next_word(&mut reader).unwrap(),
+                Some(b'a' as u64)
+            );
+        }
+    }
+}
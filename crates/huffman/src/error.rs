@@ -0,0 +1,36 @@
+use std::io;
+
+use thiserror::Error;
+
+use crate::stream::MAX_BLOCK_SIZE;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Unexpected end of input")]
+    UnexpectedEof,
+    /// stored tree or code lengths do not describe a valid prefix code
+    #[error("Invalid Huffman tree: {0}")]
+    InvalidTreeShape(&'static str),
+    #[error("Not valid Huffman encoded data: {0}")]
+    CorruptData(&'static str),
+    /// word outside of the tree alphabet was given to the encoder
+    #[error("Word {0} has no Huffman code")]
+    UnknownWord(u64),
+    #[error("Block size {0} is not between 1 and {MAX_BLOCK_SIZE} bytes")]
+    InvalidBlockSize(u64),
+    #[error("Invalid Huffman parameters: {0}")]
+    InvalidParameters(&'static str),
+    #[error(transparent)]
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => Error::UnexpectedEof,
+            _ => Error::Io(err),
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
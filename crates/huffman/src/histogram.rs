@@ -1,8 +1,9 @@
 use bit_utils::read::ToWordIter;
-use anyhow::Result;
 use indicatif::ProgressBar;
 use std::{convert::TryFrom, io::Read};
 
+use crate::error::{Error, Result};
+
 #[derive(Debug, Clone)]
 pub struct Histogram {
     freq: Vec<u64>,
@@ -41,36 +42,40 @@ impl Histogram {
 }
 
 impl<'a> TryFrom<&'a [u64]> for Histogram {
-    type Error = anyhow::Error;
+    type Error = Error;
 
     fn try_from(value: &'a [u64]) -> std::result::Result<Self, Self::Error> {
         if !value.len().is_power_of_two() {
-            anyhow::bail!("Slice is not a valid histogram");
+            return Err(Error::InvalidParameters(
+                "histogram length is not a power of two",
+            ));
         }
 
         let word_size = value.len().trailing_zeros();
 
-        return Ok(Self {
+        Ok(Self {
             freq: value.to_vec(),
             word_size: word_size as u8,
-        });
+        })
     }
 }
 
 impl TryFrom<Vec<u64>> for Histogram {
-    type Error = anyhow::Error;
+    type Error = Error;
 
     fn try_from(value: Vec<u64>) -> std::result::Result<Self, Self::Error> {
         if !value.len().is_power_of_two() {
-            anyhow::bail!("Slice is not a valid histogram");
+            return Err(Error::InvalidParameters(
+                "histogram length is not a power of two",
+            ));
         }
 
         let word_size = value.len().trailing_zeros();
 
-        return Ok(Self {
+        Ok(Self {
             freq: value,
             word_size: word_size as u8,
-        });
+        })
     }
 }
 
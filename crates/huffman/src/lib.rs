@@ -1,6 +1,14 @@
+pub mod adaptive;
+pub mod error;
 pub mod histogram;
 pub mod tree;
 pub mod utils;
+mod package_merge;
+mod stream;
 mod zip;
 
-pub use zip::{compress, decompress};
+pub use adaptive::{compress_adaptive, decompress_adaptive};
+pub use error::{Error, Result};
+pub use stream::{compress_stream, decompress_stream, validate_block_size, MAX_BLOCK_SIZE};
+pub use tree::TreeFormat;
+pub use zip::{compress, compress_with, decompress, decompress_with, Options};
@@ -0,0 +1,92 @@
+/// Computes optimal code lengths limited to `max_length` bits with the package-merge
+/// algorithm. Every word gets a code, including those with zero frequency.
+/// Returns `None` when `1 << max_length` codes are not enough for all words.
+pub fn code_lengths(freq: &[u64], max_length: u8) -> Option<Vec<u8>> {
+    let count = freq.len();
+
+    if count < 2 || (max_length as u32) < usize::BITS && count > 1 << max_length {
+        return None;
+    }
+
+    let mut words = (0..count).collect::<Vec<_>>();
+    words.sort_by_key(|&word| freq[word]);
+
+    // only first `2 * count - 2` items of every level may ever be selected
+    let limit = 2 * count - 2;
+    let mut levels = Vec::with_capacity(max_length as usize);
+    // package weights may exceed the total frequency, as packages repeat words
+    let mut packages: Vec<u128> = Vec::new();
+
+    // build lists from the deepest level up, merging words with packages
+    // made of item pairs from the level below
+    for _ in 0..max_length {
+        let mut merged = Vec::with_capacity(limit);
+        let mut is_word = Vec::with_capacity(limit);
+        let (mut i, mut j) = (0, 0);
+
+        while merged.len() < limit && (i < count || j < packages.len()) {
+            if j >= packages.len() || (i < count && freq[words[i]] as u128 <= packages[j]) {
+                merged.push(freq[words[i]] as u128);
+                is_word.push(true);
+                i += 1;
+            } else {
+                merged.push(packages[j]);
+                is_word.push(false);
+                j += 1;
+            }
+        }
+
+        packages = merged
+            .chunks_exact(2)
+            .map(|pair| pair[0] + pair[1])
+            .collect();
+        levels.push(is_word);
+    }
+
+    // select items from the top level down, every selected word gets one bit longer
+    let mut lengths = vec![0u8; count];
+    let mut selected = limit;
+
+    for is_word in levels.iter().rev() {
+        let word_count = is_word[..selected].iter().filter(|&&w| w).count();
+
+        for &word in &words[..word_count] {
+            lengths[word] += 1;
+        }
+
+        selected = 2 * (selected - word_count);
+    }
+
+    Some(lengths)
+}
+
+#[cfg(test)]
+mod test {
+    use super::code_lengths;
+
+    #[test]
+    fn should_limit_code_lengths() {
+        let freq = [1, 1, 2, 4, 8, 16, 32, 64];
+
+        assert_eq!(
+            code_lengths(&freq, 7).unwrap(),
+            vec![7, 7, 6, 5, 4, 3, 2, 1]
+        );
+        assert_eq!(
+            code_lengths(&freq, 4).unwrap(),
+            vec![4, 4, 4, 4, 4, 4, 3, 1]
+        );
+        assert_eq!(code_lengths(&freq, 3).unwrap(), vec![3; 8]);
+        assert_eq!(code_lengths(&freq, 2), None);
+    }
+
+    #[test]
+    fn should_assign_codes_to_zero_frequency_words() {
+        let lengths = code_lengths(&[0, 0, 0, 5, 0, 0, 9, 0], 5).unwrap();
+        let kraft_sum: f64 = lengths.iter().map(|&l| 0.5f64.powi(l as i32)).sum();
+
+        assert!(lengths.iter().all(|&l| (1..=5).contains(&l)));
+        assert_eq!(kraft_sum, 1.0);
+        assert_eq!((lengths[3], lengths[6]), (2, 1));
+    }
+}
@@ -0,0 +1,157 @@
+use bit_utils::{
+    elias,
+    read::{BitReader, ToWordIter},
+    write::WordWriter,
+};
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use crate::{
+    error::{Error, Result},
+    histogram::Histogram,
+    tree::{DecodeTable, HuffmanTree, TreeFormat},
+    zip::Options,
+};
+
+/// Largest block of the streaming mode, the encoder keeps a whole block in
+/// memory.
+pub const MAX_BLOCK_SIZE: usize = 1 << 24;
+
+pub fn validate_block_size(block_size: usize) -> Result<()> {
+    if !(1..=MAX_BLOCK_SIZE).contains(&block_size) {
+        return Err(Error::InvalidBlockSize(block_size as u64));
+    }
+
+    Ok(())
+}
+
+/// Compresses `input` without seeking, by splitting it into blocks of at most
+/// `block_size` bytes. Every block is prefixed with its length and its own tree,
+/// stream is terminated by an empty block.
+pub fn compress_stream(
+    options: Options,
+    block_size: usize,
+    input: impl Read,
+    output: impl Write,
+) -> Result<()> {
+    validate_block_size(block_size)?;
+
+    let word_size = options.word_size;
+    let mut reader = BufReader::new(input);
+    let mut writer = WordWriter::new(BufWriter::new(output));
+    let mut block = Vec::with_capacity(block_size);
+
+    loop {
+        block.clear();
+        (&mut reader)
+            .take(block_size as u64)
+            .read_to_end(&mut block)?;
+
+        elias::write_gamma_elias(&mut writer, block.len() as u64)?;
+
+        if block.is_empty() {
+            break;
+        }
+
+        let histogram = Histogram::read(&mut &block[..], word_size, None)?;
+        let tree = options.build_tree(histogram)?;
+        tree.write_as(options.tree_format, &mut writer)?;
+
+        for word in (&block[..]).word_iter(word_size) {
+            writer.write(tree.encode_word(word)?)?;
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Decompresses output of [`compress_stream`].
+pub fn decompress_stream(
+    tree_format: TreeFormat,
+    input: impl Read,
+    output: impl Write,
+) -> Result<()> {
+    let mut reader = BitReader::new(BufReader::new(input));
+    let mut writer = WordWriter::new(BufWriter::new(output));
+
+    loop {
+        let block_size = elias::read_gamma_elias(&mut reader)?;
+
+        if block_size == 0 {
+            break;
+        }
+
+        if block_size > MAX_BLOCK_SIZE as u64 {
+            return Err(Error::CorruptData("block is larger than the maximum"));
+        }
+
+        let tree = HuffmanTree::read_as(tree_format, &mut reader)?;
+        let table = DecodeTable::new(&tree, DecodeTable::DEFAULT_INDEX_BITS);
+        let word_size = tree.get_word_size() as u64;
+        let mut cursor = block_size * 8;
+        let mut words = [0; DecodeTable::MAX_WORDS];
+
+        while cursor > 0 {
+            // next block follows the last word, which must not be overread
+            let limit = cursor.div_ceil(word_size).min(words.len() as u64) as usize;
+            let count = tree.decode_next_words_with(&table, &mut reader, &mut words[..limit])?;
+
+            for &word in &words[..count] {
+                writer.write((word, word_size.min(cursor) as usize))?;
+                cursor = cursor.saturating_sub(word_size);
+            }
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{error::Error, tree::TreeFormat, zip::Options};
+
+    use super::{compress_stream, decompress_stream, MAX_BLOCK_SIZE};
+
+    fn roundtrip(word_size: u8, block_size: usize, data: &[u8]) {
+        for tree_format in [TreeFormat::Full, TreeFormat::Canonical] {
+            let options = Options {
+                tree_format,
+                ..Options::new(word_size)
+            };
+            let mut compressed = Vec::new();
+            compress_stream(options, block_size, data, &mut compressed).unwrap();
+            let mut output = Vec::new();
+            decompress_stream(tree_format, &compressed[..], &mut output).unwrap();
+
+            assert_eq!(output, data);
+        }
+    }
+
+    #[test]
+    fn roundtrip_empty() {
+        roundtrip(8, 16, &[]);
+    }
+
+    #[test]
+    fn roundtrip_multiple_blocks() {
+        let data = b"the quick brown fox jumps over the lazy dog".repeat(10);
+
+        roundtrip(8, 7, &data);
+        roundtrip(5, 13, &data);
+        roundtrip(11, 64, &data);
+        roundtrip(3, 1, &data);
+    }
+
+    #[test]
+    fn should_reject_invalid_block_size() {
+        for block_size in [0, MAX_BLOCK_SIZE + 1] {
+            assert!(matches!(
+                compress_stream(Options::new(8), block_size, &b"abc"[..], Vec::new()),
+                Err(Error::InvalidBlockSize(size)) if size == block_size as u64
+            ));
+        }
+    }
+}
@@ -2,12 +2,26 @@ use std::{
     cmp::Reverse,
     collections::{BinaryHeap, HashMap},
     convert::TryInto,
-    io::{self, Read, Write},
+    io::{Read, Write},
     iter::FromIterator,
 };
 
-use crate::histogram::Histogram;
-use bit_utils::{read::BitReader, write::WordWriter};
+use crate::{
+    error::{Error, Result},
+    histogram::Histogram,
+    package_merge,
+};
+use bit_utils::{elias, read::BitReader, write::WordWriter, zigzag};
+
+/// How a tree is serialized into the compressed stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TreeFormat {
+    /// tree shape followed by the value of every leaf
+    #[default]
+    Full,
+    /// code lengths only, codes are rebuilt canonically from them
+    Canonical,
+}
 
 pub struct HuffmanTree {
     word_size: u8,
@@ -16,13 +30,84 @@ pub struct HuffmanTree {
 }
 
 impl HuffmanTree {
-    pub fn encode_word(&self, word: u64) -> (u64, usize) {
-        self.dictionary.get(&word).unwrap().clone()
+    pub fn encode_word(&self, word: u64) -> Result<(u64, usize)> {
+        self.dictionary
+            .get(&word)
+            .copied()
+            .ok_or(Error::UnknownWord(word))
+    }
+
+    pub fn decode_next_word(&self, reader: &mut BitReader<impl Read>) -> Result<u64> {
+        self.decode_from(0, reader)
     }
 
-    pub fn decode_next_word(&self, reader: &mut BitReader<impl Read>) -> Result<u64, io::Error> {
-        let mut head = 0;
+    /// Same as [`HuffmanTree::decode_next_word`], but resolves up to `table`
+    /// index bits with a single lookup and walks the tree only for longer codes.
+    pub fn decode_next_word_with(
+        &self,
+        table: &DecodeTable,
+        reader: &mut BitReader<impl Read>,
+    ) -> Result<u64> {
+        let mut word = [0];
+        self.decode_next_words_with(table, reader, &mut word)?;
+
+        Ok(word[0])
+    }
+
+    /// Decodes up to `words.len()` words into `words`, returning how many were
+    /// decoded. All words whose codes fit into a single lookup are resolved at
+    /// once, at least one word is decoded unless `words` is empty.
+    pub fn decode_next_words_with(
+        &self,
+        table: &DecodeTable,
+        reader: &mut BitReader<impl Read>,
+        words: &mut [u64],
+    ) -> Result<usize> {
+        if words.is_empty() {
+            return Ok(0);
+        }
+
+        let (index, available) = reader.peek(table.index_bits)?;
+
+        match &table.entries[index as usize] {
+            TableEntry::Words {
+                words: decoded,
+                ends,
+                count,
+            } if ends[0] as usize <= available => {
+                let mut count = (*count as usize).min(words.len());
+
+                // input ends within the lookup window
+                while ends[count - 1] as usize > available {
+                    count -= 1;
+                }
+
+                for (word, &decoded) in words.iter_mut().zip(&decoded[..count]) {
+                    *word = decoded as u64;
+                }
+                reader.consume(ends[count - 1] as usize);
+
+                Ok(count)
+            }
+            &TableEntry::Node(head) if available == table.index_bits => {
+                reader.consume(table.index_bits);
+                words[0] = self.decode_from(head, reader)?;
+
+                Ok(1)
+            }
+            _ => {
+                words[0] = self.decode_from(0, reader)?;
+
+                Ok(1)
+            }
+        }
+    }
 
+    fn decode_from(
+        &self,
+        mut head: usize,
+        reader: &mut BitReader<impl Read>,
+    ) -> Result<u64> {
         while head < self.nodes.len() {
             let bit = reader.read(1)?;
 
@@ -33,12 +118,27 @@ impl HuffmanTree {
             }
         }
 
-        Ok((head - self.nodes.len()).try_into().unwrap())
+        Ok((head - self.nodes.len()) as u64)
+    }
+
+    /// Tree of a single word. Both branches of the root lead to it, so the word
+    /// still gets a one-bit code and every bit decodes to it.
+    fn single(word_size: u8, word: usize) -> Self {
+        Self {
+            word_size,
+            nodes: vec![(1 + word, 1 + word)],
+            dictionary: HashMap::from([(word as u64, (0, 1))]),
+        }
     }
 
     fn compute_dictionary_from_nodes(&mut self) {
         let mut dictionary = HashMap::new();
 
+        if self.nodes.is_empty() {
+            self.dictionary = dictionary;
+            return;
+        }
+
         let mut queue = vec![(0, vec![])];
 
         while let Some((id, path)) = queue.pop() {
@@ -48,10 +148,9 @@ impl HuffmanTree {
             left_path.push(false);
             if left >= self.nodes.len() {
                 let converted_value = left_path.iter().fold(0, |a, i| (a << 1) | (*i as u64));
-                dictionary.insert(
-                    (left - self.nodes.len()) as u64,
-                    (converted_value, left_path.len()),
-                );
+                dictionary
+                    .entry((left - self.nodes.len()) as u64)
+                    .or_insert((converted_value, left_path.len()));
             } else {
                 queue.push((left, left_path));
             }
@@ -60,10 +159,9 @@ impl HuffmanTree {
             right_path.push(true);
             if right >= self.nodes.len() {
                 let converted_value = right_path.iter().fold(0, |a, i| (a << 1) | (*i as u64));
-                dictionary.insert(
-                    (right - self.nodes.len()) as u64,
-                    (converted_value, right_path.len()),
-                );
+                dictionary
+                    .entry((right - self.nodes.len()) as u64)
+                    .or_insert((converted_value, right_path.len()));
             } else {
                 queue.push((right, right_path));
             }
@@ -72,7 +170,9 @@ impl HuffmanTree {
         self.dictionary = dictionary;
     }
 
-    pub fn write(&self, writer: &mut WordWriter<impl Write>) -> Result<(), io::Error> {
+    /// Writes tree shape, listing only the words present in the tree.
+    pub fn write(&self, writer: &mut WordWriter<impl Write>) -> Result<()> {
+        self.check_not_empty()?;
         writer.write(((self.word_size - 2) as u64, 4))?; // we allow word sizes 2-17
 
         let mut queue = vec![self.nodes[0].1, self.nodes[0].0];
@@ -92,10 +192,13 @@ impl HuffmanTree {
         Ok(())
     }
 
-    pub fn read(reader: &mut BitReader<impl Read>) -> Result<Self, io::Error> {
+    pub fn read(reader: &mut BitReader<impl Read>) -> Result<Self> {
         let word_size = reader.read(4)? as usize + 2;
 
-        let mut nodes = vec![(0, 0); (1usize << word_size) - 1];
+        // leaves are stored as `max_nodes + word` until the actual number
+        // of inner nodes is known
+        let max_nodes = (1usize << word_size) - 1;
+        let mut nodes = vec![(0, 0); max_nodes];
 
         let mut path = vec![0];
 
@@ -113,24 +216,38 @@ impl HuffmanTree {
                 } else if nodes[node].1 == 0 {
                     nodes[node].1 = word as usize;
                 } else {
-                    panic!("Unexpected error occurred");
+                    return Err(Error::InvalidTreeShape("node has more than two children"));
                 }
             } else {
                 free_node += 1;
 
+                if free_node == max_nodes {
+                    return Err(Error::InvalidTreeShape("tree has more nodes than words"));
+                }
+
                 if nodes[node].0 == 0 {
                     nodes[node].0 = free_node;
                     path.push(node);
                 } else if nodes[node].1 == 0 {
                     nodes[node].1 = free_node;
                 } else {
-                    panic!("Unexpected error occurred");
+                    return Err(Error::InvalidTreeShape("node has more than two children"));
                 }
 
                 path.push(free_node);
             }
         }
 
+        nodes.truncate(free_node + 1);
+        let inner_count = nodes.len();
+        for (left, right) in nodes.iter_mut() {
+            for child in [left, right] {
+                if *child >= max_nodes {
+                    *child = *child - max_nodes + inner_count;
+                }
+            }
+        }
+
         let mut output = Self {
             word_size: word_size as u8,
             dictionary: HashMap::new(),
@@ -142,20 +259,349 @@ impl HuffmanTree {
         Ok(output)
     }
 
+    fn check_not_empty(&self) -> Result<()> {
+        if self.nodes.is_empty() {
+            return Err(Error::InvalidParameters(
+                "tree without words can not be written",
+            ));
+        }
+
+        Ok(())
+    }
+
     pub fn get_word_size(&self) -> u8 {
         self.word_size
     }
+
+    /// Builds tree from histogram with no code longer than `max_code_length` bits.
+    /// Regular Huffman tree is returned when it already fits, otherwise optimal
+    /// limited code lengths are found with package-merge and codes are canonical.
+    pub fn with_max_code_length(
+        histogram: Histogram,
+        max_code_length: u8,
+    ) -> Result<Self> {
+        let word_size = histogram.get_word_size();
+
+        if max_code_length == 0 || max_code_length as u32 > u64::BITS {
+            return Err(Error::InvalidParameters(
+                "max code length must be between 1 and 64 bits",
+            ));
+        }
+
+        let tree = Self::from(histogram.clone());
+
+        if tree.get_max_code_length() <= max_code_length {
+            return Ok(tree);
+        }
+
+        let (words, freq): (Vec<_>, Vec<_>) = histogram
+            .get_freq()
+            .iter()
+            .enumerate()
+            .filter(|(_, &freq)| freq > 0)
+            .unzip();
+        let limited = package_merge::code_lengths(&freq, max_code_length)
+            .ok_or(Error::InvalidParameters(
+                "too many words for max code length",
+            ))?;
+
+        let mut lengths = vec![0u8; 1 << word_size];
+        for (word, length) in words.into_iter().zip(limited) {
+            lengths[word] = length;
+        }
+
+        Self::from_code_lengths(word_size, &lengths)
+    }
+
+    pub fn get_max_code_length(&self) -> u8 {
+        self.dictionary
+            .values()
+            .map(|&(_, length)| length.min(u8::MAX as usize) as u8)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Code length of every word, `0` for words without a code.
+    pub fn code_lengths(&self) -> Vec<u8> {
+        let mut lengths = vec![0u8; 1 << self.word_size];
+
+        for (&word, &(_, length)) in &self.dictionary {
+            lengths[word as usize] = length as u8;
+        }
+
+        lengths
+    }
+
+    /// Builds tree with canonical codes for given code lengths: words are ordered
+    /// by code length and then by value, and get consecutive codes in this order.
+    /// Lengths must describe a complete prefix code, the only exception is
+    /// a single word with a one-bit code.
+    pub fn from_code_lengths(word_size: u8, lengths: &[u8]) -> Result<Self> {
+        if lengths.len() != 1 << word_size {
+            return Err(Error::InvalidTreeShape("code lengths do not match word size"));
+        }
+
+        let mut words = lengths
+            .iter()
+            .enumerate()
+            .filter(|(_, &length)| length > 0)
+            .map(|(word, &length)| (length as usize, word))
+            .collect::<Vec<_>>();
+        words.sort_unstable();
+
+        if words.iter().any(|&(length, _)| length > u64::BITS as usize) {
+            return Err(Error::InvalidTreeShape("code length exceeds 64 bits"));
+        }
+
+        let kraft_sum = words
+            .iter()
+            .map(|&(length, _)| 1u128 << (u64::BITS as usize - length))
+            .sum::<u128>();
+
+        if let [(1, word)] = words[..] {
+            return Ok(Self::single(word_size, word));
+        }
+
+        if words.len() < 2 || kraft_sum != 1u128 << u64::BITS {
+            return Err(Error::InvalidTreeShape(
+                "code lengths do not form a complete prefix code",
+            ));
+        }
+
+        let join_count = words.len() - 1;
+        let mut nodes = vec![(0, 0); join_count];
+        let mut dictionary = HashMap::new();
+        let mut free_node = 0;
+        let mut code = 0u64;
+        let mut last_length = words[0].0;
+
+        for (length, word) in words {
+            code = code.checked_shl((length - last_length) as u32).unwrap_or(0);
+            last_length = length;
+
+            let mut head = 0;
+            for bit in (0..length).rev() {
+                let (left, right) = &mut nodes[head];
+                let child = if (code >> bit) & 1 == 0 { left } else { right };
+
+                if bit == 0 {
+                    *child = join_count + word;
+                } else {
+                    if *child == 0 {
+                        free_node += 1;
+                        *child = free_node;
+                    }
+
+                    head = *child;
+                }
+            }
+
+            dictionary.insert(word as u64, (code, length));
+            code = code.wrapping_add(1);
+        }
+
+        Ok(Self {
+            word_size,
+            nodes,
+            dictionary,
+        })
+    }
+
+    /// Returns tree with the same code lengths, but canonical code assignment.
+    /// Fails if some code is longer than 64 bits.
+    pub fn to_canonical(&self) -> Result<Self> {
+        Self::from_code_lengths(self.word_size, &self.code_lengths())
+    }
+
+    /// Writes code lengths of present words only. Every present word is stored as
+    /// gamma coded count of absent words before it, followed by gamma coded
+    /// difference with the previous length, so runs of equal lengths take a single
+    /// bit per word. Count of absent words at the end closes the list.
+    /// Tree must be canonical, see [`HuffmanTree::to_canonical`].
+    pub fn write_canonical(&self, writer: &mut WordWriter<impl Write>) -> Result<()> {
+        self.check_not_empty()?;
+        writer.write(((self.word_size - 2) as u64, 4))?;
+
+        let mut last_length = 0i64;
+        let mut absent = 0;
+        for length in self.code_lengths() {
+            if length == 0 {
+                absent += 1;
+                continue;
+            }
+
+            elias::write_gamma_elias(writer, absent)?;
+            elias::write_gamma_elias(writer, zigzag::encode(length as i64 - last_length))?;
+            last_length = length as i64;
+            absent = 0;
+        }
+
+        if absent > 0 {
+            elias::write_gamma_elias(writer, absent)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_canonical(reader: &mut BitReader<impl Read>) -> Result<Self> {
+        let word_size = reader.read(4)? as u8 + 2;
+
+        let mut lengths = vec![0u8; 1 << word_size];
+        let mut last_length = 0i64;
+        let mut word = 0;
+        while word < lengths.len() {
+            let absent = elias::read_gamma_elias(reader)?;
+
+            if absent > (lengths.len() - word) as u64 {
+                return Err(Error::InvalidTreeShape("code lengths do not match word size"));
+            }
+
+            word += absent as usize;
+
+            if word == lengths.len() {
+                break;
+            }
+
+            last_length += zigzag::decode(elias::read_gamma_elias(reader)?);
+            lengths[word] = match last_length.try_into() {
+                Ok(length) if length > 0 => length,
+                _ => return Err(Error::InvalidTreeShape("invalid code length")),
+            };
+            word += 1;
+        }
+
+        Self::from_code_lengths(word_size, &lengths)
+    }
+
+    pub fn write_as(
+        &self,
+        format: TreeFormat,
+        writer: &mut WordWriter<impl Write>,
+    ) -> Result<()> {
+        match format {
+            TreeFormat::Full => self.write(writer),
+            TreeFormat::Canonical => self.write_canonical(writer),
+        }
+    }
+
+    pub fn read_as(
+        format: TreeFormat,
+        reader: &mut BitReader<impl Read>,
+    ) -> Result<Self> {
+        match format {
+            TreeFormat::Full => Self::read(reader),
+            TreeFormat::Canonical => Self::read_canonical(reader),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TableEntry {
+    /// codes of the first `count` words fit into the index, code of word `i`
+    /// ends after `ends[i]` bits of it. Words are at most 17 bits wide, as
+    /// limited by the stored trees, narrow ones keep the table small.
+    Words {
+        words: [u32; DecodeTable::MAX_WORDS],
+        ends: [u8; DecodeTable::MAX_WORDS],
+        count: u8,
+    },
+    /// code is longer than the index, decoding continues from this node
+    Node(usize),
+}
+
+/// Lookup table for [`HuffmanTree::decode_next_word_with`], indexed by the
+/// next `index_bits` bits of input.
+pub struct DecodeTable {
+    index_bits: usize,
+    entries: Vec<TableEntry>,
+}
+
+impl DecodeTable {
+    /// Index size used by the decompressors, a 4096 entry table.
+    pub const DEFAULT_INDEX_BITS: u8 = 12;
+
+    /// Largest number of words resolved by a single lookup.
+    pub const MAX_WORDS: usize = 2;
+
+    pub fn new(tree: &HuffmanTree, index_bits: u8) -> Self {
+        let index_bits = (index_bits.min(tree.get_max_code_length()) as usize)
+            .clamp(1, BitReader::<&[u8]>::MAX_PEEK);
+
+        let entries = (0..1u64 << index_bits)
+            .map(|index| {
+                let mut words = [0; Self::MAX_WORDS];
+                let mut ends = [0; Self::MAX_WORDS];
+                let mut count = 0;
+                let mut head = 0;
+
+                for depth in 1..=index_bits {
+                    let (left, right) = tree.nodes[head];
+                    head = if (index >> (index_bits - depth)) & 1 == 0 {
+                        left
+                    } else {
+                        right
+                    };
+
+                    if head >= tree.nodes.len() {
+                        words[count] = (head - tree.nodes.len()) as u32;
+                        ends[count] = depth as u8;
+                        count += 1;
+                        head = 0;
+
+                        if count == Self::MAX_WORDS {
+                            break;
+                        }
+                    }
+                }
+
+                match count {
+                    // code is longer than the index
+                    0 => TableEntry::Node(head),
+                    _ => TableEntry::Words {
+                        words,
+                        ends,
+                        count: count as u8,
+                    },
+                }
+            })
+            .collect();
+
+        Self {
+            index_bits,
+            entries,
+        }
+    }
 }
 
 impl From<Histogram> for HuffmanTree {
     fn from(value: Histogram) -> Self {
         let word_size = value.get_word_size();
-        let histogram = value.get_freq();
+        // only present words get codes, leaf `i` of the tree is `words[i]`
+        let (words, histogram): (Vec<_>, Vec<_>) = value
+            .get_freq()
+            .iter()
+            .enumerate()
+            .filter(|(_, &freq)| freq > 0)
+            .unzip();
+
+        match words[..] {
+            [] => {
+                return HuffmanTree {
+                    word_size,
+                    nodes: Vec::new(),
+                    dictionary: HashMap::new(),
+                }
+            }
+            [word] => return HuffmanTree::single(word_size, word),
+            _ => {}
+        }
+
         let mut tree = vec![usize::MAX; histogram.len() * 2 - 1];
 
         #[derive(Debug)]
         struct Node {
             freq: u64,
+            height: usize,
             index: usize,
         }
 
@@ -169,22 +615,33 @@ impl From<Histogram> for HuffmanTree {
 
         impl PartialOrd for Node {
             fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-                self.freq.partial_cmp(&other.freq)
+                Some(self.cmp(other))
             }
         }
 
         impl Ord for Node {
             fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-                self.freq.cmp(&other.freq)
+                // on equal frequencies join shallower subtrees first, which keeps
+                // codes of rare words short
+                self.freq
+                    .cmp(&other.freq)
+                    .then(self.height.cmp(&other.height))
             }
         }
 
-        let mut heap = BinaryHeap::from_iter(histogram.iter().enumerate().map(|(word, &freq)| {
-            Reverse(Node {
-                index: word,
-                freq: freq,
-            })
-        }).collect::<Vec<_>>());
+        let mut heap = BinaryHeap::from_iter(
+            histogram
+                .iter()
+                .enumerate()
+                .map(|(word, &freq)| {
+                    Reverse(Node {
+                        index: word,
+                        height: 0,
+                        freq,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        );
         let mut free_space = histogram.len();
 
         loop {
@@ -200,6 +657,7 @@ impl From<Histogram> for HuffmanTree {
             tree[second.index] = new_index;
             heap.push(Reverse(Node {
                 freq: new_freq,
+                height: first.height.max(second.height) + 1,
                 index: new_index,
             }));
         }
@@ -214,9 +672,9 @@ impl From<Histogram> for HuffmanTree {
             let (left, right) = &mut tree_2[full_tree_length - 1 - parent];
 
             if *left == 0 {
-                *left = join_count + current;
+                *left = join_count + words[current];
             } else if *right == 0 {
-                *right = join_count + current;
+                *right = join_count + words[current];
             } else {
                 debug_assert!(false, "Something wrong happen");
             }
@@ -257,7 +715,11 @@ impl From<Histogram> for HuffmanTree {
 mod test {
     use std::{collections::HashMap, convert::TryInto};
 
-    use crate::{histogram::Histogram, tree::HuffmanTree};
+    use crate::{
+        error::Error,
+        histogram::Histogram,
+        tree::{DecodeTable, HuffmanTree, TreeFormat},
+    };
     use bit_utils::{read::BitReader, write::WordWriter};
 
     #[test]
@@ -322,7 +784,19 @@ mod test {
 
         tree.compute_dictionary_from_nodes();
 
-        assert_eq!(tree.dictionary, HashMap::new());
+        assert_eq!(
+            tree.dictionary,
+            HashMap::from([
+                (0b000, (0b1111110, 7)),
+                (0b001, (0b1111111, 7)),
+                (0b010, (0b111110, 6)),
+                (0b011, (0b11110, 5)),
+                (0b100, (0b1110, 4)),
+                (0b101, (0b110, 3)),
+                (0b110, (0b10, 2)),
+                (0b111, (0b0, 1)),
+            ])
+        );
 
         let mut buffer = Vec::new();
         let mut writer: WordWriter<_> = (&mut buffer).into();
@@ -354,7 +828,7 @@ mod test {
 
     #[test]
     fn should_correctly_read_tree() {
-        let buffer = vec![
+        let buffer = [
             0b00011111, 0b01110011, 0b01011000, 0b10110101, 0b00100010, 0b01000000,
         ];
         let mut reader = BitReader::new(&buffer[..]);
@@ -382,7 +856,7 @@ mod test {
 
     #[test]
     fn should_correctly_read_balanced_tree() {
-        let buffer = vec![
+        let buffer = [
             0b00010010, 0b11110001, 0b10111100, 0b01001111, 0b10100010, 0b10000000,
         ];
         let mut reader = BitReader::new(&buffer[..]);
@@ -442,4 +916,222 @@ mod test {
 
         assert_eq!(tree.dictionary, tree_2.dictionary);
     }
+
+    #[test]
+    fn should_assign_canonical_codes() {
+        assert!(HuffmanTree::from_code_lengths(3, &[3, 3, 2, 0, 0, 2, 3, 0]).is_err());
+        assert!(HuffmanTree::from_code_lengths(3, &[3, 3, 2, 2, 2, 2, 0, 0]).is_err());
+
+        let tree = HuffmanTree::from_code_lengths(3, &[3, 4, 2, 0, 3, 2, 4, 3]).unwrap();
+        assert_eq!(
+            tree.dictionary,
+            HashMap::from([
+                (0b000, (0b100, 3)),
+                (0b001, (0b1110, 4)),
+                (0b010, (0b00, 2)),
+                (0b100, (0b101, 3)),
+                (0b101, (0b01, 2)),
+                (0b110, (0b1111, 4)),
+                (0b111, (0b110, 3)),
+            ])
+        );
+    }
+
+    #[test]
+    fn canonical_tree_keeps_code_lengths() {
+        let histogram: Histogram = vec![1, 1, 2, 4, 8, 16, 32, 64].try_into().unwrap();
+        let tree = HuffmanTree::from(histogram);
+        let canonical = tree.to_canonical().unwrap();
+
+        assert_eq!(canonical.code_lengths(), tree.code_lengths());
+        assert_eq!(
+            canonical.nodes,
+            vec![(14, 1), (13, 2), (12, 3), (11, 4), (10, 5), (9, 6), (7, 8)]
+        );
+    }
+
+    #[test]
+    fn canonical_serialization_roundtrip() {
+        let histogram: Histogram = vec![4, 2, 2, 1, 1, 1, 1, 1].try_into().unwrap();
+        let tree = HuffmanTree::from(histogram).to_canonical().unwrap();
+
+        let mut buffer = Vec::new();
+        {
+            let mut writer = WordWriter::new(&mut buffer);
+            tree.write_canonical(&mut writer).unwrap();
+        }
+
+        let mut reader = BitReader::new(&buffer[..]);
+        let tree_2 = HuffmanTree::read_canonical(&mut reader).unwrap();
+
+        assert_eq!(tree.dictionary, tree_2.dictionary);
+        assert_eq!(tree.nodes, tree_2.nodes);
+    }
+
+    #[test]
+    fn should_limit_code_length() {
+        let histogram: Histogram = vec![1, 1, 2, 4, 8, 16, 32, 64].try_into().unwrap();
+        let tree = HuffmanTree::with_max_code_length(histogram.clone(), 7).unwrap();
+        assert_eq!(tree.get_max_code_length(), 7);
+        assert_eq!(tree.nodes, HuffmanTree::from(histogram.clone()).nodes);
+
+        let tree = HuffmanTree::with_max_code_length(histogram.clone(), 4).unwrap();
+        assert_eq!(tree.code_lengths(), vec![4, 4, 4, 4, 4, 4, 3, 1]);
+        assert_eq!(tree.encode_word(0b111).unwrap(), (0b0, 1));
+        assert_eq!(tree.encode_word(0b110).unwrap(), (0b100, 3));
+
+        assert!(HuffmanTree::with_max_code_length(histogram, 2).is_err());
+    }
+
+    #[test]
+    fn should_fit_fibonacci_codes_into_64_bits() {
+        let mut freq = vec![0u64; 128];
+        let (mut a, mut b) = (1u64, 1u64);
+        for value in freq.iter_mut().skip(38) {
+            *value = a;
+            (a, b) = (b, a + b);
+        }
+        let histogram: Histogram = freq.try_into().unwrap();
+
+        assert!(HuffmanTree::from(histogram.clone()).get_max_code_length() > 64);
+
+        let tree = HuffmanTree::with_max_code_length(histogram, 64).unwrap();
+        assert_eq!(tree.get_max_code_length(), 64);
+
+        let mut buffer = Vec::new();
+        {
+            let mut writer = WordWriter::new(&mut buffer);
+            for word in 38..128 {
+                writer.write(tree.encode_word(word).unwrap()).unwrap();
+            }
+        }
+
+        let mut reader = BitReader::new(&buffer[..]);
+        for word in 38..128 {
+            assert_eq!(tree.decode_next_word(&mut reader).unwrap(), word);
+        }
+    }
+
+    #[test]
+    fn table_decoder_matches_tree_walk() {
+        let histogram: Histogram = vec![1, 1, 2, 4, 8, 16, 32, 64].try_into().unwrap();
+        let tree = HuffmanTree::from(histogram);
+        let words = [7, 0, 3, 1, 6, 6, 2, 5, 4, 7];
+
+        let mut buffer = Vec::new();
+        {
+            let mut writer = WordWriter::new(&mut buffer);
+            for word in words {
+                writer.write(tree.encode_word(word).unwrap()).unwrap();
+            }
+        }
+
+        for index_bits in [1, 3, 7, 12] {
+            let table = DecodeTable::new(&tree, index_bits);
+            let mut reader = BitReader::new(&buffer[..]);
+
+            for word in words {
+                assert_eq!(
+                    tree.decode_next_word_with(&table, &mut reader).unwrap(),
+                    word
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn table_decoder_resolves_several_words() {
+        let histogram: Histogram = vec![1, 1, 2, 4, 8, 16, 32, 64].try_into().unwrap();
+        let tree = HuffmanTree::from(histogram);
+        // codes of 7 and 6 are one and two bits long, several fit into a lookup
+        let words = [7, 7, 6, 7, 0, 7, 7, 7, 7, 6];
+
+        let mut buffer = Vec::new();
+        {
+            let mut writer = WordWriter::new(&mut buffer);
+            for word in words {
+                writer.write(tree.encode_word(word).unwrap()).unwrap();
+            }
+        }
+
+        let table = DecodeTable::new(&tree, 12);
+        let mut reader = BitReader::new(&buffer[..]);
+        let mut decoded = [0; DecodeTable::MAX_WORDS];
+
+        let first = tree.decode_next_words_with(&table, &mut reader, &mut decoded).unwrap();
+        assert_eq!(first, DecodeTable::MAX_WORDS);
+        assert_eq!(decoded[..first], words[..first]);
+        let count = tree.decode_next_words_with(&table, &mut reader, &mut decoded[..1]).unwrap();
+        assert_eq!(decoded[..count], words[first..first + 1]);
+
+        let mut rest = Vec::<u64>::new();
+        while rest.len() < words.len() - first - 1 {
+            // padding after the last code decodes as well, so it is not asked for
+            let limit = (words.len() - first - 1 - rest.len()).min(decoded.len());
+            let count = tree
+                .decode_next_words_with(&table, &mut reader, &mut decoded[..limit])
+                .unwrap();
+            rest.extend(&decoded[..count]);
+        }
+        assert_eq!(rest, words[first + 1..]);
+    }
+
+    #[test]
+    fn should_skip_absent_words() {
+        let histogram: Histogram = vec![0, 3, 0, 0, 1, 0, 1, 0].try_into().unwrap();
+        let tree = HuffmanTree::from(histogram);
+
+        assert_eq!(tree.nodes, vec![(3, 1), (6, 8)]);
+        assert_eq!(tree.code_lengths(), vec![0, 1, 0, 0, 2, 0, 2, 0]);
+
+        for format in [TreeFormat::Full, TreeFormat::Canonical] {
+            let tree = tree.to_canonical().unwrap();
+            let mut buffer = Vec::new();
+            {
+                let mut writer = WordWriter::new(&mut buffer);
+                tree.write_as(format, &mut writer).unwrap();
+            }
+
+            let mut reader = BitReader::new(&buffer[..]);
+            let tree_2 = HuffmanTree::read_as(format, &mut reader).unwrap();
+
+            assert_eq!(tree.dictionary, tree_2.dictionary);
+            assert_eq!(tree.nodes, tree_2.nodes);
+        }
+    }
+
+    #[test]
+    fn should_build_single_word_tree() {
+        let histogram: Histogram = vec![0, 0, 7, 0].try_into().unwrap();
+        let tree = HuffmanTree::from(histogram);
+
+        assert_eq!(tree.nodes, vec![(3, 3)]);
+        assert_eq!(tree.encode_word(2).unwrap(), (0, 1));
+        assert!(matches!(tree.encode_word(1), Err(Error::UnknownWord(1))));
+        assert_eq!(tree.code_lengths(), vec![0, 0, 1, 0]);
+
+        for format in [TreeFormat::Full, TreeFormat::Canonical] {
+            let mut buffer = Vec::new();
+            {
+                let mut writer = WordWriter::new(&mut buffer);
+                tree.write_as(format, &mut writer).unwrap();
+            }
+
+            let mut reader = BitReader::new(&buffer[..]);
+            let tree_2 = HuffmanTree::read_as(format, &mut reader).unwrap();
+
+            assert_eq!(tree_2.nodes, tree.nodes);
+            assert_eq!(tree_2.dictionary, tree.dictionary);
+        }
+    }
+
+    #[test]
+    fn should_not_write_empty_tree() {
+        let histogram: Histogram = vec![0, 0, 0, 0].try_into().unwrap();
+        let tree = HuffmanTree::from(histogram);
+
+        assert!(tree.nodes.is_empty());
+        assert!(tree.write(&mut WordWriter::new(Vec::new())).is_err());
+        assert!(HuffmanTree::from_code_lengths(2, &[0, 0, 0, 0]).is_err());
+    }
 }
@@ -1,51 +1,115 @@
-use anyhow::Result;
-use std::io::{BufReader, BufWriter, Read, Seek, Write};
 use bit_utils::{
+    read::{BitReader, ToWordIter},
     write::WordWriter,
-    read::{BitReader, ToWordIter}
 };
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
 
 use crate::{
+    error::{Error, Result},
     histogram::Histogram,
-    tree::HuffmanTree,
+    tree::{DecodeTable, HuffmanTree, TreeFormat},
 };
 
+/// Parameters of the static Huffman coder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    pub word_size: u8,
+    pub tree_format: TreeFormat,
+    /// longest allowed code, at most 64 bits so every code fits into a register
+    pub max_code_length: u8,
+}
+
+impl Options {
+    pub fn new(word_size: u8) -> Self {
+        Self {
+            word_size,
+            tree_format: TreeFormat::default(),
+            max_code_length: u64::BITS as u8,
+        }
+    }
+
+    pub fn build_tree(&self, histogram: Histogram) -> Result<HuffmanTree> {
+        let tree = HuffmanTree::with_max_code_length(histogram, self.max_code_length)?;
+
+        Ok(match self.tree_format {
+            TreeFormat::Full => tree,
+            TreeFormat::Canonical => tree.to_canonical()?,
+        })
+    }
+}
+
 pub fn compress(word_size: u8, input: impl Read + Seek, output: impl Write) -> Result<()> {
+    compress_with(Options::new(word_size), input, output)
+}
+
+pub fn compress_with(options: Options, input: impl Read + Seek, output: impl Write) -> Result<()> {
+    let word_size = options.word_size;
     let mut reader = BufReader::new(input);
     let file_size = reader.seek(std::io::SeekFrom::End(0))?;
+    let mut writer = BufWriter::new(output);
+    writer.write_all(&file_size.to_be_bytes())?;
+
+    // there are no words to code, so the tree is omitted as well
+    if file_size == 0 {
+        writer.flush()?;
+        return Ok(());
+    }
+
     reader.rewind()?;
     let histogram = Histogram::read(&mut reader, word_size, None)?;
-    let tree = HuffmanTree::from(histogram);
+    let tree = options.build_tree(histogram)?;
     reader.rewind()?;
 
-    let mut writer = BufWriter::new(output);
-    writer.write(&file_size.to_be_bytes())?;
     let mut word_writer = WordWriter::new(writer);
-    tree.write(&mut word_writer)?;
+    tree.write_as(options.tree_format, &mut word_writer)?;
 
     for word in reader.word_iter(word_size) {
-        word_writer.write(tree.encode_word(word))?;
+        word_writer.write(tree.encode_word(word)?)?;
     }
 
     Ok(())
 }
 
 pub fn decompress(input: impl Read, output: impl Write) -> Result<()> {
+    decompress_with(TreeFormat::Full, input, output)
+}
+
+/// Decompresses output of [`compress_with`], tree format has to match the one used for compression.
+pub fn decompress_with(
+    tree_format: TreeFormat,
+    input: impl Read,
+    output: impl Write,
+) -> Result<()> {
     let mut reader = BufReader::new(input);
     let mut file_size = [0u8; 8];
     reader.read_exact(&mut file_size)?;
     let file_size = u64::from_be_bytes(file_size);
+
+    if file_size == 0 {
+        return Ok(());
+    }
+
     let mut reader = BitReader::new(reader);
-    let tree = HuffmanTree::read(&mut reader)?;
+    let tree = HuffmanTree::read_as(tree_format, &mut reader)?;
+    let table = DecodeTable::new(&tree, DecodeTable::DEFAULT_INDEX_BITS);
 
     let writer = BufWriter::new(output);
     let mut writer = WordWriter::new(writer);
-    let mut cursor = file_size * 8;
+    let word_size = tree.get_word_size() as u64;
+    let mut cursor = file_size
+        .checked_mul(8)
+        .ok_or(Error::CorruptData("file size is too large"))?;
+    let mut words = [0; DecodeTable::MAX_WORDS];
 
     while cursor > 0 {
-        let word = tree.decode_next_word(&mut reader)?;
-        writer.write((word, (tree.get_word_size() as usize).min(cursor as usize)))?;
-        cursor = cursor.saturating_sub(tree.get_word_size() as u64);
+        // never decode past the last word, padding bits follow it
+        let limit = cursor.div_ceil(word_size).min(words.len() as u64) as usize;
+        let count = tree.decode_next_words_with(&table, &mut reader, &mut words[..limit])?;
+
+        for &word in &words[..count] {
+            writer.write((word, word_size.min(cursor) as usize))?;
+            cursor = cursor.saturating_sub(word_size);
+        }
     }
 
     Ok(())
@@ -53,14 +117,16 @@ pub fn decompress(input: impl Read, output: impl Write) -> Result<()> {
 
 #[cfg(test)]
 mod test {
-    use crate::utils::SeekableSliceReader;
+    use std::io::Cursor;
 
-    use super::{compress, decompress};
+    use crate::{error::Error, tree::TreeFormat};
+
+    use super::{compress, compress_with, decompress, decompress_with, Options};
 
     #[test]
     fn roundtrip_1() {
         let mut compressed = Vec::new();
-        compress(10, SeekableSliceReader::new(&[10]), &mut compressed).unwrap();
+        compress(10, Cursor::new(&[10][..]), &mut compressed).unwrap();
         let mut output = Vec::new();
         decompress(&compressed[..], &mut output).unwrap();
 
@@ -70,10 +136,101 @@ mod test {
     #[test]
     fn roundtrip_2() {
         let mut compressed = Vec::new();
-        compress(10, SeekableSliceReader::new(&[10, 10]), &mut compressed).unwrap();
+        compress(10, Cursor::new(&[10, 10][..]), &mut compressed).unwrap();
         let mut output = Vec::new();
         decompress(&compressed[..], &mut output).unwrap();
 
         assert_eq!(output, vec![10, 10]);
     }
+
+    #[test]
+    fn roundtrip_empty() {
+        for tree_format in [TreeFormat::Full, TreeFormat::Canonical] {
+            let options = Options {
+                tree_format,
+                ..Options::new(8)
+            };
+
+            let mut compressed = Vec::new();
+            compress_with(options, Cursor::new(&[][..]), &mut compressed).unwrap();
+            let mut output = Vec::new();
+            decompress_with(tree_format, &compressed[..], &mut output).unwrap();
+
+            assert_eq!(compressed, 0u64.to_be_bytes());
+            assert!(output.is_empty());
+        }
+    }
+
+    #[test]
+    fn roundtrip_single_word() {
+        let data = [0x42; 100];
+
+        for tree_format in [TreeFormat::Full, TreeFormat::Canonical] {
+            let options = Options {
+                tree_format,
+                ..Options::new(8)
+            };
+
+            let mut compressed = Vec::new();
+            compress_with(options, Cursor::new(&data), &mut compressed).unwrap();
+            let mut output = Vec::new();
+            decompress_with(tree_format, &compressed[..], &mut output).unwrap();
+
+            assert_eq!(output, data);
+            // size, tree and a single bit per word
+            assert!(compressed.len() <= 8 + 4 + 100 / 8 + 1);
+        }
+    }
+
+    #[test]
+    fn roundtrip_canonical() {
+        let data = b"abracadabra abracadabra";
+        let options = Options {
+            tree_format: TreeFormat::Canonical,
+            ..Options::new(12)
+        };
+
+        let mut compressed = Vec::new();
+        compress_with(options, Cursor::new(data), &mut compressed).unwrap();
+        let mut full = Vec::new();
+        compress(12, Cursor::new(data), &mut full).unwrap();
+        let mut output = Vec::new();
+        decompress_with(TreeFormat::Canonical, &compressed[..], &mut output).unwrap();
+
+        assert_eq!(output, data);
+        // absent words of the 4096 word alphabet cost nothing in either format
+        assert!(compressed.len() < 2 * data.len());
+        assert!(full.len() < 2 * data.len());
+    }
+
+    #[test]
+    fn roundtrip_limited_code_length() {
+        let data = (0..200u8)
+            .flat_map(|i| vec![i; 1 + i as usize / 8])
+            .collect::<Vec<_>>();
+        let options = Options {
+            max_code_length: 9,
+            ..Options::new(8)
+        };
+
+        let mut compressed = Vec::new();
+        compress_with(options, Cursor::new(&data), &mut compressed).unwrap();
+        let mut output = Vec::new();
+        decompress(&compressed[..], &mut output).unwrap();
+
+        assert_eq!(output, data);
+    }
+
+    #[test]
+    fn should_report_truncated_input() {
+        let mut compressed = Vec::new();
+        compress(8, Cursor::new(&b"abracadabra"[..]), &mut compressed).unwrap();
+
+        for length in [3, 9, compressed.len() - 1] {
+            assert!(matches!(
+                decompress(&compressed[..length], Vec::new()),
+                Err(Error::UnexpectedEof)
+            ));
+        }
+    }
 }
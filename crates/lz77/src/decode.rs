@@ -0,0 +1,55 @@
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use bit_utils::{elias, read::BitReader};
+
+use crate::{
+    error::{Error, Result},
+    Options, MAX_MATCH_LENGTH,
+};
+
+/// Decodes output of [`crate::encode`] written with the same `options`.
+pub fn decode(options: Options, reader: impl Read, writer: impl Write) -> Result<()> {
+    options.validate()?;
+
+    let mut reader = BitReader::new(BufReader::new(reader));
+    let mut writer = BufWriter::new(writer);
+
+    let window_size = options.window_size as usize;
+    let min_match = options.min_match;
+    let mut history = Vec::new();
+
+    loop {
+        if reader.read(1)? == 0 {
+            history.push(reader.read(u8::BITS as usize)? as u8);
+        } else {
+            let distance = elias::read_gamma_elias(&mut reader)? as usize;
+
+            if distance == 0 {
+                break;
+            }
+
+            let length =
+                (elias::read_gamma_elias(&mut reader)? as usize).saturating_add(min_match as usize);
+
+            if distance > window_size || distance > history.len() || length > MAX_MATCH_LENGTH {
+                return Err(Error::InvalidMatch { distance, length });
+            }
+
+            let start = history.len() - distance;
+            for i in start..start + length {
+                history.push(history[i]);
+            }
+        }
+
+        if history.len() >= 2 * window_size + MAX_MATCH_LENGTH {
+            let obsolete = history.len() - window_size;
+            writer.write_all(&history[..obsolete])?;
+            history.drain(..obsolete);
+        }
+    }
+
+    writer.write_all(&history)?;
+    writer.flush()?;
+
+    Ok(())
+}
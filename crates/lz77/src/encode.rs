@@ -0,0 +1,71 @@
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use bit_utils::{elias, write::WordWriter};
+
+use crate::{error::Result, hash_chain::HashChain, Options, MAX_MATCH_LENGTH};
+
+/// Encodes `reader` as a sequence of literals and back-references into
+/// the last `options.window_size` bytes.
+///
+/// Every token is a flag bit followed by either a literal byte (`0`), or by
+/// gamma coded distance and gamma coded length above the minimal one (`1`).
+/// Distance `0` terminates the stream. `options` are not stored, the decoder
+/// is given the same ones.
+pub fn encode(reader: impl Read, writer: impl Write, options: Options) -> Result<()> {
+    options.validate()?;
+
+    let window_size = options.window_size as usize;
+    let min_match = options.min_match as usize;
+    let mut reader = BufReader::new(reader);
+    let mut writer = WordWriter::new(BufWriter::new(writer));
+
+    let mut chain = HashChain::new(window_size, min_match);
+    // input from position `base`, holding the window and lookahead
+    let mut buffer = Vec::new();
+    let mut base = 0;
+    let mut cursor = 0;
+    let mut is_eof = false;
+
+    loop {
+        if !is_eof && buffer.len() - (cursor - base) < MAX_MATCH_LENGTH {
+            let obsolete = (cursor - base).saturating_sub(window_size);
+            if obsolete >= window_size {
+                buffer.drain(..obsolete);
+                base += obsolete;
+            }
+
+            let chunk = window_size.max(MAX_MATCH_LENGTH) as u64;
+            is_eof = (&mut reader).take(chunk).read_to_end(&mut buffer)? < chunk as usize;
+        }
+
+        let Some(&byte) = buffer.get(cursor - base) else {
+            break;
+        };
+
+        let length = match chain.find(&buffer, base, cursor) {
+            Some((distance, length)) if length >= min_match => {
+                writer.write((1, 1))?;
+                elias::write_gamma_elias(&mut writer, distance as u64)?;
+                elias::write_gamma_elias(&mut writer, (length - min_match) as u64)?;
+                length
+            }
+            _ => {
+                writer.write((0, 1))?;
+                writer.write((byte as u64, u8::BITS as usize))?;
+                1
+            }
+        };
+
+        for position in cursor..cursor + length {
+            chain.insert(&buffer[position - base..], position);
+        }
+
+        cursor += length;
+    }
+
+    writer.write((1, 1))?;
+    elias::write_gamma_elias(&mut writer, 0)?;
+    writer.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use std::io;
+
+use thiserror::Error;
+
+use crate::MAX_WINDOW_SIZE;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Unexpected end of input")]
+    UnexpectedEof,
+    #[error("Window size {0} is not between 1 and {MAX_WINDOW_SIZE} bytes")]
+    InvalidWindowSize(u64),
+    #[error("Minimal match length {0} is shorter than 2 bytes")]
+    InvalidMinMatch(u64),
+    /// back-reference reaching before the start of data or out of the window
+    #[error("Match of {length} bytes at distance {distance} is out of range")]
+    InvalidMatch { distance: usize, length: usize },
+    #[error(transparent)]
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => Error::UnexpectedEof,
+            _ => Error::Io(err),
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,116 @@
+use crate::MAX_MATCH_LENGTH;
+
+const HASH_BITS: u32 = 16;
+/// how many earlier positions with the same hash are compared at most
+const MAX_CHAIN_LENGTH: usize = 128;
+const NONE: usize = usize::MAX;
+
+/// Match finder keeping, for every hash of the next few bytes, a chain
+/// of positions where these bytes were seen, most recent first.
+pub struct HashChain {
+    window_size: usize,
+    hash_length: usize,
+    /// most recent position of every hash
+    head: Vec<usize>,
+    /// previous position with the same hash, indexed by position modulo window
+    prev: Vec<usize>,
+}
+
+impl HashChain {
+    pub fn new(window_size: usize, min_match: usize) -> Self {
+        Self {
+            window_size,
+            hash_length: min_match.min(4),
+            head: vec![NONE; 1 << HASH_BITS],
+            prev: vec![NONE; window_size],
+        }
+    }
+
+    fn hash(&self, bytes: &[u8]) -> usize {
+        let value = bytes[..self.hash_length]
+            .iter()
+            .fold(0u32, |hash, &byte| (hash << 8) | byte as u32);
+
+        (value.wrapping_mul(0x9E3779B1) >> (u32::BITS - HASH_BITS)) as usize
+    }
+
+    /// Records that `bytes` start at `position`. Positions too close to the end
+    /// of input to be hashed are skipped.
+    pub fn insert(&mut self, bytes: &[u8], position: usize) {
+        if bytes.len() < self.hash_length {
+            return;
+        }
+
+        let hash = self.hash(bytes);
+        self.prev[position % self.window_size] = self.head[hash];
+        self.head[hash] = position;
+    }
+
+    /// Finds the longest match for `buffer[cursor - base..]` within the window,
+    /// returning its distance and length. `buffer` starts at position `base`
+    /// and must hold the whole window before `cursor`.
+    pub fn find(&self, buffer: &[u8], base: usize, cursor: usize) -> Option<(usize, usize)> {
+        let target = &buffer[cursor - base..];
+        let target = &target[..target.len().min(MAX_MATCH_LENGTH)];
+
+        if target.len() < self.hash_length {
+            return None;
+        }
+
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidate = self.head[self.hash(target)];
+
+        for _ in 0..MAX_CHAIN_LENGTH {
+            if candidate == NONE || cursor - candidate > self.window_size {
+                break;
+            }
+
+            let length = buffer[candidate - base..]
+                .iter()
+                .zip(target)
+                .take_while(|(a, b)| a == b)
+                .count();
+
+            if best.is_none_or(|(_, best_length)| length > best_length) {
+                best = Some((cursor - candidate, length));
+
+                if length == target.len() {
+                    break;
+                }
+            }
+
+            let next = self.prev[candidate % self.window_size];
+            if next >= candidate {
+                break;
+            }
+            candidate = next;
+        }
+
+        best
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HashChain;
+
+    #[test]
+    fn should_find_longest_recent_match() {
+        let data = b"abcdXabcdeYabcdeZabcde";
+        let mut chain = HashChain::new(32, 3);
+
+        for position in 0..17 {
+            chain.insert(&data[position..], position);
+        }
+
+        assert_eq!(chain.find(data, 0, 17), Some((6, 5)));
+
+        // matches further than the window are ignored
+        let mut chain = HashChain::new(5, 3);
+        for position in 0..17 {
+            chain.insert(&data[position..], position);
+        }
+
+        assert_eq!(chain.find(data, 0, 17), None);
+    }
+}
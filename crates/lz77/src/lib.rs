@@ -0,0 +1,149 @@
+pub mod decode;
+pub mod encode;
+pub mod error;
+mod hash_chain;
+
+pub use decode::decode;
+pub use encode::encode;
+pub use error::{Error, Result};
+
+/// Largest supported window, distances are never longer than this.
+pub const MAX_WINDOW_SIZE: u32 = 1 << 24;
+
+/// Longest match a single token may describe.
+pub const MAX_MATCH_LENGTH: usize = 1 << 12;
+
+/// Parameters of the LZSS coder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// how far back, in bytes, matches are searched for
+    pub window_size: u32,
+    /// shortest match worth a back-reference, shorter ones are sent as literals
+    pub min_match: u8,
+}
+
+impl Options {
+    pub fn new(window_size: u32, min_match: u8) -> Self {
+        Self {
+            window_size,
+            min_match,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=MAX_WINDOW_SIZE).contains(&self.window_size) {
+            return Err(Error::InvalidWindowSize(self.window_size as u64));
+        }
+
+        if self.min_match < 2 {
+            return Err(Error::InvalidMinMatch(self.min_match as u64));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self::new(1 << 15, 3)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bit_utils::{elias, write::WordWriter};
+
+    use super::{decode, encode, Error, Options, MAX_MATCH_LENGTH};
+
+    fn roundtrip(data: &[u8], options: Options) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        encode(data, &mut compressed, options).unwrap();
+        let mut output = Vec::new();
+        decode(options, &compressed[..], &mut output).unwrap();
+
+        assert_eq!(output, data);
+
+        compressed
+    }
+
+    #[test]
+    fn roundtrip_options() {
+        let text =
+            b"{\"level\":\"info\",\"message\":\"request served\",\"status\":200}\n".repeat(50);
+        let zeros = vec![0u8; 3 * MAX_MATCH_LENGTH + 7];
+        let noise = (0..5000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect::<Vec<_>>();
+
+        for options in [
+            Options::default(),
+            Options::new(1, 2),
+            Options::new(7, 3),
+            Options::new(64, 5),
+            Options::new(1 << 20, 255),
+        ] {
+            for data in [&b""[..], b"a", b"abcabcabcabc", &text, &zeros, &noise] {
+                roundtrip(data, options);
+            }
+        }
+    }
+
+    #[test]
+    fn should_use_back_references() {
+        let text = b"the quick brown fox jumps over the lazy dog. ".repeat(100);
+        let compressed = roundtrip(&text, Options::default());
+
+        assert!(compressed.len() * 20 < text.len());
+    }
+
+    #[test]
+    fn should_reject_invalid_options() {
+        assert!(matches!(
+            encode(&b"abc"[..], Vec::new(), Options::new(0, 3)),
+            Err(Error::InvalidWindowSize(0))
+        ));
+        assert!(matches!(
+            encode(&b"abc"[..], Vec::new(), Options::new(1 << 25, 3)),
+            Err(Error::InvalidWindowSize(_))
+        ));
+        assert!(matches!(
+            encode(&b"abc"[..], Vec::new(), Options::new(1024, 1)),
+            Err(Error::InvalidMinMatch(1))
+        ));
+        assert!(matches!(
+            decode(Options::new(0, 3), &b""[..], Vec::new()),
+            Err(Error::InvalidWindowSize(0))
+        ));
+    }
+
+    #[test]
+    fn should_report_malformed_input() {
+        let compressed = roundtrip(b"abcabcabcabc", Options::default());
+
+        assert!(matches!(
+            decode(
+                Options::default(),
+                &compressed[..compressed.len() / 2],
+                Vec::new()
+            ),
+            Err(Error::UnexpectedEof)
+        ));
+
+        // match reaching before the start of data
+        let mut invalid = Vec::new();
+        let mut writer = WordWriter::new(&mut invalid);
+        writer.write((1, 1)).unwrap();
+        elias::write_gamma_elias(&mut writer, 5).unwrap();
+        elias::write_gamma_elias(&mut writer, 0).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        assert!(matches!(
+            decode(Options::new(64, 3), &invalid[..], Vec::new()),
+            Err(Error::InvalidMatch {
+                distance: 5,
+                length: 3
+            })
+        ));
+    }
+}
@@ -1,51 +1,94 @@
 use std::io::{BufReader, BufWriter, Read, Write};
 
-use anyhow::{bail, Result};
-use bit_utils::{elias, read::BitReader, zigzag};
+use bit_utils::{elias, read::BitReader, truncated};
+use huffman::adaptive::AdaptiveHuffman;
 
-use crate::dictionary::Dictionary;
+use crate::{
+    dictionary::{Dictionary, PruningStrategy},
+    error::{Error, Result},
+    header, index_count, TokenFormat,
+};
 
 pub fn decode(reader: impl Read, writer: impl Write) -> Result<()> {
+    decode_with(TokenFormat::Gamma, reader, writer)
+}
+
+pub fn decode_with(format: TokenFormat, reader: impl Read, writer: impl Write) -> Result<()> {
     let mut reader = BitReader::new(BufReader::new(reader));
-    let mut writer = BufWriter::new(writer);
+    let strategy = header::read(&mut reader)?;
 
-    let strategy = elias::read_gamma_elias(&mut reader)?;
-    let strategy = zigzag::decode(strategy);
+    decode_tokens(strategy, format, reader, writer)
+}
 
-    let mut dictionary = Dictionary::new(strategy.into());
+/// Decodes a stream without the header, written by [`crate::encode_raw`]
+/// with the same `strategy` and `format`.
+pub fn decode_raw(
+    strategy: PruningStrategy,
+    format: TokenFormat,
+    reader: impl Read,
+    writer: impl Write,
+) -> Result<()> {
+    decode_tokens(
+        strategy,
+        format,
+        BitReader::new(BufReader::new(reader)),
+        writer,
+    )
+}
+
+fn decode_tokens(
+    strategy: PruningStrategy,
+    format: TokenFormat,
+    mut reader: BitReader<impl Read>,
+    writer: impl Write,
+) -> Result<()> {
+    let mut writer = BufWriter::new(writer);
+    let mut dictionary = Dictionary::new(strategy);
+    let mut literals = AdaptiveHuffman::new(u8::BITS as u8);
 
     loop {
-        let index = elias::read_gamma_elias(&mut reader)?;
+        let indices = index_count(&dictionary);
+        let index = match format {
+            TokenFormat::Gamma => elias::read_gamma_elias(&mut reader)?,
+            TokenFormat::Compact => truncated::read_truncated_binary(&mut reader, indices)?,
+        };
 
         if index == dictionary.len() as u64 + 1 {
             break;
         }
 
         if index == dictionary.len() as u64 + 2 {
-            let index = elias::read_gamma_elias(&mut reader)?;
-            let Some(sentence) = dictionary.get(index as usize) else {
-                bail!("Not valid LZ78 encoded file");
+            let index = match format {
+                TokenFormat::Gamma => elias::read_gamma_elias(&mut reader)?,
+                TokenFormat::Compact => {
+                    truncated::read_truncated_binary(&mut reader, dictionary.len() as u64 + 1)?
+                }
             };
-            writer.write(&sentence)?;
+            if !dictionary.write_phrase(index as usize, &mut writer)? {
+                return Err(Error::IndexOutOfRange(index));
+            }
             break;
         }
-        
-        let word = reader.read(8)? as u8;
-        
-        if index == 0 {
-            writer.write(&[word])?;
-            dictionary.add(&[word]).unwrap();
-        } else {
-            let Some(mut sentence) = dictionary.get(index as usize) else {
-                bail!("Not valid LZ78 encoded file");
-            };
 
-            sentence.push(word);
-            writer.write(&sentence)?;
-            dictionary.add(&sentence).unwrap();
+        let word = match format {
+            TokenFormat::Gamma => reader.read(8)? as u8,
+            TokenFormat::Compact => match literals.decode_next_word(&mut reader)? {
+                Some(word) => word as u8,
+                None => return Err(Error::CorruptData("literals end within a token")),
+            },
+        };
+
+        dictionary.start_token();
+
+        if !dictionary.write_phrase(index as usize, &mut writer)? {
+            return Err(Error::IndexOutOfRange(index));
+        }
+        writer.write_all(&[word])?;
+
+        if !dictionary.add(index as usize, word) {
+            return Err(Error::CorruptData("token repeats a known phrase"));
         }
     }
 
     Ok(())
 }
-
@@ -1,98 +1,504 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{self, Write},
+    mem,
+};
 
+/// Phrases stored as a trie: every phrase is its prefix phrase extended by a
+/// single byte. Index zero stands for the empty phrase, learned ones start
+/// from one.
 pub struct Dictionary {
-    nodes: Vec<(usize, u8)>,
-    index: HashMap<Vec<u8>, usize>,
-    strategy: PruningStrategy
+    nodes: Vec<Node>,
+    /// child phrase of every (prefix index, byte) pair
+    children: HashMap<(usize, u8), usize>,
+    strategy: PruningStrategy,
+    /// phrases without extensions, ordered by eviction priority
+    evictable: BTreeSet<(u64, u64, usize)>,
+    /// number of tokens seen, serves as a clock for recency
+    tokens: u64,
+    ratio: RatioMonitor,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    prefix: usize,
+    byte: u8,
+    /// phrase length in bytes
+    length: usize,
+    /// number of phrases extending this one, only leaves are evicted
+    children: usize,
+    /// tokens built on top of this phrase, including the one adding it
+    uses: u64,
+    last_use: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PruningStrategy {
     /// never prune, dictionary grows indefinitely
     Never,
     /// when dictionary reaches specified max length, drop whole dictionary
     Drop(u64),
     /// when dictionary reaches specified max length, freeze dictionary
-    Freeze(u64)
+    Freeze(u64),
+    /// when dictionary reaches specified max length, replace the least recently
+    /// used phrase which is not a prefix of another one
+    Lru(u64),
+    /// same as [`PruningStrategy::Lru`], but replaces the least frequently used
+    /// phrase, older one on ties
+    Lfu(u64),
+    /// freeze dictionary at specified max length, and drop it once compression
+    /// ratio gets worse, as Unix `compress` does
+    Reset(u64),
+}
+
+const NEVER_TAG: u8 = 0;
+const DROP_TAG: u8 = 1;
+const FREEZE_TAG: u8 = 2;
+const LRU_TAG: u8 = 3;
+const LFU_TAG: u8 = 4;
+const RESET_TAG: u8 = 5;
+
+impl PruningStrategy {
+    /// Identifier of the strategy in stream and container headers.
+    pub fn tag(&self) -> u8 {
+        match self {
+            PruningStrategy::Never => NEVER_TAG,
+            PruningStrategy::Drop(_) => DROP_TAG,
+            PruningStrategy::Freeze(_) => FREEZE_TAG,
+            PruningStrategy::Lru(_) => LRU_TAG,
+            PruningStrategy::Lfu(_) => LFU_TAG,
+            PruningStrategy::Reset(_) => RESET_TAG,
+        }
+    }
+
+    /// Dictionary length the strategy acts at, zero for [`PruningStrategy::Never`].
+    pub fn limit(&self) -> u64 {
+        match *self {
+            PruningStrategy::Never => 0,
+            PruningStrategy::Drop(limit)
+            | PruningStrategy::Freeze(limit)
+            | PruningStrategy::Lru(limit)
+            | PruningStrategy::Lfu(limit)
+            | PruningStrategy::Reset(limit) => limit,
+        }
+    }
+
+    /// Inverse of [`PruningStrategy::tag`] and [`PruningStrategy::limit`],
+    /// `None` for unknown tags.
+    pub fn from_tag(tag: u8, limit: u64) -> Option<Self> {
+        match tag {
+            NEVER_TAG => Some(PruningStrategy::Never),
+            DROP_TAG => Some(PruningStrategy::Drop(limit)),
+            FREEZE_TAG => Some(PruningStrategy::Freeze(limit)),
+            LRU_TAG => Some(PruningStrategy::Lru(limit)),
+            LFU_TAG => Some(PruningStrategy::Lfu(limit)),
+            RESET_TAG => Some(PruningStrategy::Reset(limit)),
+            _ => None,
+        }
+    }
 }
 
+/// Strategy of a single signed integer: zero never prunes, positive values
+/// drop and negative ones freeze the dictionary.
 impl From<i64> for PruningStrategy {
     fn from(value: i64) -> Self {
         match value {
             0 => PruningStrategy::Never,
-            0.. => PruningStrategy::Drop(value as u64),
-            ..0 => PruningStrategy::Freeze(value.abs() as u64),
+            1.. => PruningStrategy::Drop(value as u64),
+            ..0 => PruningStrategy::Freeze(value.unsigned_abs()),
         }
     }
 }
 
-impl Into<i64> for PruningStrategy {
-    fn into(self) -> i64 {
-        match self {
-            PruningStrategy::Never => 0,
-            PruningStrategy::Drop(value) => value as i64,
-            PruningStrategy::Freeze(value) => -(value as i64),
-        }
-    }
+/// Compression ratio since the last reset, as bytes covered per token. It is
+/// checked every `limit` tokens once the dictionary is full.
+#[derive(Debug, Clone, Copy, Default)]
+struct RatioMonitor {
+    bytes: u64,
+    tokens: u64,
+    next_check: u64,
+    /// best ratio so far as (bytes, tokens)
+    best: (u64, u64),
 }
 
 impl Dictionary {
     pub fn new(strategy: PruningStrategy) -> Self {
-        Self { nodes: Vec::new(), index: HashMap::new(), strategy }
+        Self {
+            nodes: Vec::new(),
+            children: HashMap::new(),
+            strategy,
+            evictable: BTreeSet::new(),
+            tokens: 0,
+            ratio: RatioMonitor::default(),
+        }
     }
 
-    pub fn add(&mut self, word: &[u8]) -> Option<(usize, u8)> {
+    /// Has to be called before every token, before its first byte is looked
+    /// up by the encoder, and after its index is read by the decoder. Full
+    /// dictionary is dropped here with [`PruningStrategy::Drop`].
+    pub fn start_token(&mut self) {
         if let PruningStrategy::Drop(max_len) = self.strategy {
             if self.nodes.len() >= max_len as usize {
-                self.nodes.clear();
-                self.index.clear();
+                self.clear();
             }
-        } else if let PruningStrategy::Freeze(max_len) = self.strategy {
-            if self.nodes.len() >= max_len as usize {
-                return Some((0, *word.last().unwrap()))
+        }
+    }
+
+    /// Index of phrase `prefix` extended with `byte`, if it is known.
+    pub fn child(&self, prefix: usize, byte: u8) -> Option<usize> {
+        self.children.get(&(prefix, byte)).copied()
+    }
+
+    /// Accounts for a token of phrase `prefix` followed by `byte`. Depending on
+    /// strategy the new phrase is learned, possibly in place of another one.
+    /// Returns `false` when `prefix` is unknown or the phrase is known already,
+    /// which never happens for tokens produced by the encoder.
+    pub fn add(&mut self, prefix: usize, byte: u8) -> bool {
+        if prefix > self.nodes.len() || self.children.contains_key(&(prefix, byte)) {
+            return false;
+        }
+
+        self.tokens += 1;
+        self.touch(prefix);
+
+        match self.strategy {
+            PruningStrategy::Never | PruningStrategy::Drop(_) => self.push(prefix, byte),
+            PruningStrategy::Freeze(max_len) | PruningStrategy::Reset(max_len) => {
+                if self.nodes.len() < max_len as usize {
+                    self.push(prefix, byte);
+                }
+            }
+            PruningStrategy::Lru(max_len) | PruningStrategy::Lfu(max_len) => {
+                if self.nodes.len() < max_len as usize {
+                    self.push(prefix, byte);
+                } else if let Some(victim) = self.victim(prefix) {
+                    self.replace(victim, prefix, byte);
+                }
             }
         }
 
-        if self.index.contains_key(word) {
-            return None;
+        if let PruningStrategy::Reset(max_len) = self.strategy {
+            let length = self.phrase_length(prefix) + 1;
+            self.monitor_ratio(max_len, length as u64);
+        }
+
+        true
+    }
+
+    fn clear(&mut self) {
+        self.nodes.clear();
+        self.children.clear();
+        self.evictable.clear();
+    }
+
+    fn phrase_length(&self, index: usize) -> usize {
+        match index {
+            0 => 0,
+            _ => self.nodes[index - 1].length,
         }
+    }
+
+    fn tracks_usage(&self) -> bool {
+        matches!(
+            self.strategy,
+            PruningStrategy::Lru(_) | PruningStrategy::Lfu(_)
+        )
+    }
 
-        let entry = (!word.is_empty()).then(|| self.index.get(&word[0..(word.len() - 1)])).flatten().copied();
+    fn priority(&self, index: usize) -> (u64, u64, usize) {
+        let node = &self.nodes[index - 1];
 
-        let last_part = *word.last().unwrap();
+        match self.strategy {
+            PruningStrategy::Lfu(_) => (node.uses, node.last_use, index),
+            _ => (0, node.last_use, index),
+        }
+    }
+
+    /// Accounts for a token extending phrase `index`.
+    fn touch(&mut self, index: usize) {
+        if index == 0 || !self.tracks_usage() {
+            return;
+        }
 
-        let v = (entry.unwrap_or(0), last_part);
+        let leaf = self.nodes[index - 1].children == 0;
+        if leaf {
+            self.evictable.remove(&self.priority(index));
+        }
 
-        self.nodes.push(v);
-        self.index.insert(word.to_vec(), self.nodes.len());
+        let node = &mut self.nodes[index - 1];
+        node.uses += 1;
+        node.last_use = self.tokens;
 
-        Some(v)
+        if leaf {
+            self.evictable.insert(self.priority(index));
+        }
     }
 
-    pub fn get(&self, mut index: usize) -> Option<Vec<u8>> {
-        let mut word_buf = vec![];
-        while let Some(&(next_index, word)) = self.nodes.get(index - 1) {
-            word_buf.push(word);
-            index = next_index;
+    fn node(&self, prefix: usize, byte: u8) -> Node {
+        Node {
+            prefix,
+            byte,
+            length: self.phrase_length(prefix) + 1,
+            children: 0,
+            uses: 1,
+            last_use: self.tokens,
+        }
+    }
+
+    fn push(&mut self, prefix: usize, byte: u8) {
+        self.nodes.push(self.node(prefix, byte));
+        self.link(self.nodes.len());
+    }
+
+    /// Phrase to be replaced, `prefix` is about to get an extension and is
+    /// kept. There is none when the prefix is the only leaf left.
+    fn victim(&self, prefix: usize) -> Option<usize> {
+        self.evictable
+            .iter()
+            .map(|&(_, _, index)| index)
+            .find(|&index| index != prefix)
+    }
+
+    fn replace(&mut self, victim: usize, prefix: usize, byte: u8) {
+        self.evictable.remove(&self.priority(victim));
+
+        let old = self.nodes[victim - 1];
+        self.children.remove(&(old.prefix, old.byte));
+
+        if old.prefix != 0 {
+            self.nodes[old.prefix - 1].children -= 1;
+
+            if self.nodes[old.prefix - 1].children == 0 {
+                self.evictable.insert(self.priority(old.prefix));
+            }
+        }
+
+        self.nodes[victim - 1] = self.node(prefix, byte);
+        self.link(victim);
+    }
+
+    /// Registers phrase `index`, which has just been stored, with its prefix.
+    fn link(&mut self, index: usize) {
+        let Node { prefix, byte, .. } = self.nodes[index - 1];
+        self.children.insert((prefix, byte), index);
+
+        if !self.tracks_usage() {
+            return;
+        }
 
-            if next_index == 0 {
-                break;
+        if prefix != 0 {
+            if self.nodes[prefix - 1].children == 0 {
+                self.evictable.remove(&self.priority(prefix));
             }
+
+            self.nodes[prefix - 1].children += 1;
+        }
+
+        self.evictable.insert(self.priority(index));
+    }
+
+    fn monitor_ratio(&mut self, max_len: u64, length: u64) {
+        let ratio = &mut self.ratio;
+        ratio.bytes += length;
+        ratio.tokens += 1;
+
+        if (self.nodes.len() as u64) < max_len {
+            return;
+        }
+
+        if ratio.next_check == 0 {
+            ratio.next_check = ratio.tokens + max_len.max(1);
+            return;
         }
 
-        if index != 0 {
-            None
+        if ratio.tokens < ratio.next_check {
+            return;
+        }
+
+        // compare bytes per token without division
+        let (best_bytes, best_tokens) = ratio.best;
+        if ratio.bytes as u128 * best_tokens as u128 >= best_bytes as u128 * ratio.tokens as u128 {
+            ratio.best = (ratio.bytes, ratio.tokens);
+            ratio.next_check = ratio.tokens + max_len.max(1);
         } else {
-            word_buf.reverse();
-            Some(word_buf)
+            self.ratio = RatioMonitor::default();
+            self.clear();
         }
     }
 
+    /// Writes phrase `index` to `writer`. Returns `false` for unknown index.
+    ///
+    /// Prefix links lead from the last byte towards the root, so they are
+    /// reversed on the way up, and the bytes are written from the first one
+    /// on the way back, which restores the links.
+    pub fn write_phrase(&mut self, index: usize, writer: &mut impl Write) -> io::Result<bool> {
+        if index > self.nodes.len() {
+            return Ok(false);
+        }
+
+        let (mut previous, mut current) = (0, index);
+        while current != 0 {
+            let node = &mut self.nodes[current - 1];
+            (previous, current) = (current, mem::replace(&mut node.prefix, previous));
+        }
+
+        // links are restored even once writing fails
+        let mut result = Ok(());
+        let (mut next, mut current) = (0, previous);
+        while current != 0 {
+            let node = &mut self.nodes[current - 1];
+            if result.is_ok() {
+                result = writer.write_all(&[node.byte]);
+            }
+            (next, current) = (current, mem::replace(&mut node.prefix, next));
+        }
+
+        result.map(|_| true)
+    }
+
+    /// Index of phrase `word`, walking the trie from its root.
     pub fn find(&self, word: &[u8]) -> Option<usize> {
-        self.index.get(word).copied()
+        word.iter()
+            .try_fold(0, |prefix, &byte| self.child(prefix, byte))
     }
 
     pub fn len(&self) -> usize {
         self.nodes.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Dictionary, PruningStrategy};
+
+    /// Adds a token of `word` as the encoder would, returning its prefix index
+    /// and last byte, or `None` when `word` is known already.
+    fn add(dictionary: &mut Dictionary, word: &[u8]) -> Option<(usize, u8)> {
+        dictionary.start_token();
+
+        let (&byte, prefix) = word.split_last().unwrap();
+        let prefix = dictionary.find(prefix).unwrap_or(0);
+
+        dictionary.add(prefix, byte).then_some((prefix, byte))
+    }
+
+    fn get(dictionary: &mut Dictionary, index: usize) -> Option<Vec<u8>> {
+        let mut phrase = Vec::new();
+        dictionary
+            .write_phrase(index, &mut phrase)
+            .unwrap()
+            .then_some(phrase)
+    }
+
+    #[test]
+    fn strategy_should_survive_tag_encoding() {
+        for strategy in [
+            PruningStrategy::Never,
+            PruningStrategy::Drop(4096),
+            PruningStrategy::Freeze(4096),
+            PruningStrategy::Lru(1),
+            PruningStrategy::Lfu(4096),
+            PruningStrategy::Reset(u64::MAX),
+        ] {
+            assert_eq!(
+                PruningStrategy::from_tag(strategy.tag(), strategy.limit()),
+                Some(strategy)
+            );
+        }
+
+        assert_eq!(PruningStrategy::from(-16), PruningStrategy::Freeze(16));
+        assert_eq!(PruningStrategy::from(16), PruningStrategy::Drop(16));
+    }
+
+    #[test]
+    fn write_phrase_should_keep_trie_intact() {
+        let mut dictionary = Dictionary::new(PruningStrategy::Never);
+
+        for word in [&b"a"[..], b"ab", b"abc", b"b"] {
+            assert!(add(&mut dictionary, word).is_some());
+        }
+
+        assert_eq!(get(&mut dictionary, 3), Some(b"abc".to_vec()));
+        assert_eq!(get(&mut dictionary, 3), Some(b"abc".to_vec()));
+        assert_eq!(get(&mut dictionary, 5), None);
+
+        // writer fails after the first byte
+        let mut output = [0u8; 1];
+        assert!(dictionary.write_phrase(3, &mut &mut output[..]).is_err());
+        assert_eq!(output, *b"a");
+
+        assert_eq!(dictionary.find(b"abc"), Some(3));
+        assert_eq!(get(&mut dictionary, 2), Some(b"ab".to_vec()));
+        assert_eq!(get(&mut dictionary, 3), Some(b"abc".to_vec()));
+    }
+
+    #[test]
+    fn drop_should_clear_before_next_token() {
+        let mut dictionary = Dictionary::new(PruningStrategy::Drop(2));
+
+        for word in [&b"a"[..], b"b"] {
+            assert!(add(&mut dictionary, word).is_some());
+        }
+        assert_eq!(dictionary.len(), 2);
+
+        assert_eq!(add(&mut dictionary, b"a"), Some((0, b'a')));
+        assert_eq!(dictionary.len(), 1);
+        assert_eq!(get(&mut dictionary, 2), None);
+        assert_eq!(get(&mut dictionary, 0), Some(vec![]));
+    }
+
+    #[test]
+    fn lru_should_replace_least_recently_used_leaf() {
+        let mut dictionary = Dictionary::new(PruningStrategy::Lru(3));
+
+        for word in [&b"a"[..], b"b", b"c"] {
+            assert!(add(&mut dictionary, word).is_some());
+        }
+
+        // `a` is used and gets an extension, `b` is the oldest leaf
+        assert_eq!(add(&mut dictionary, b"ab"), Some((1, b'b')));
+        assert_eq!(dictionary.find(b"ab"), Some(2));
+        assert_eq!(dictionary.find(b"b"), None);
+
+        // `a` is not a leaf anymore, so `c` goes next
+        assert_eq!(add(&mut dictionary, b"d"), Some((0, b'd')));
+        assert_eq!(get(&mut dictionary, 3), Some(b"d".to_vec()));
+        assert_eq!(get(&mut dictionary, 2), Some(b"ab".to_vec()));
+        assert_eq!(dictionary.len(), 3);
+    }
+
+    #[test]
+    fn lfu_should_replace_least_frequently_used_leaf() {
+        let mut dictionary = Dictionary::new(PruningStrategy::Lfu(3));
+
+        for word in [&b"a"[..], b"b", b"c", b"bx"] {
+            assert!(add(&mut dictionary, word).is_some());
+        }
+
+        // `bx` replaced `a`, the only leaf used less than `b`,
+        // `c` is now older than `bx` with the same count
+        assert_eq!(dictionary.find(b"a"), None);
+        assert_eq!(add(&mut dictionary, b"d"), Some((0, b'd')));
+        assert_eq!(dictionary.find(b"c"), None);
+        assert_eq!(dictionary.find(b"bx"), Some(1));
+    }
+
+    #[test]
+    fn reset_should_drop_dictionary_when_ratio_degrades() {
+        let mut dictionary = Dictionary::new(PruningStrategy::Reset(2));
+
+        for word in [&b"a"[..], b"b", b"ab", b"c", b"d"] {
+            assert!(add(&mut dictionary, word).is_some());
+        }
+
+        // frozen, and the ratio at the first check is the best one so far
+        assert_eq!(dictionary.len(), 2);
+
+        add(&mut dictionary, b"e");
+        assert_eq!(dictionary.len(), 0);
+    }
 }
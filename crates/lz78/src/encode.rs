@@ -1,19 +1,60 @@
 use std::io::{BufReader, BufWriter, Read, Write};
 
-use anyhow::{bail, Result};
-use bit_utils::{elias, write::WordWriter, zigzag};
+use bit_utils::{elias, truncated, write::WordWriter};
+use huffman::adaptive::AdaptiveHuffman;
 
-use crate::dictionary::Dictionary;
+use crate::{
+    dictionary::{Dictionary, PruningStrategy},
+    error::Result,
+    header, index_count, TokenFormat,
+};
 
-pub fn encode(reader: impl Read, writer: impl Write, strategy: i64) -> Result<()> {
-    let mut reader = BufReader::new(reader);
+pub fn encode(reader: impl Read, writer: impl Write, strategy: PruningStrategy) -> Result<()> {
+    encode_with(reader, writer, strategy, TokenFormat::Gamma)
+}
+
+pub fn encode_with(
+    reader: impl Read,
+    writer: impl Write,
+    strategy: PruningStrategy,
+    format: TokenFormat,
+) -> Result<()> {
     let mut writer = WordWriter::new(BufWriter::new(writer));
-    elias::write_gamma_elias(&mut writer, zigzag::encode(strategy))?;
+    header::write(&mut writer, strategy)?;
+
+    encode_tokens(reader, writer, strategy, format)
+}
+
+/// Encodes without the stream header, for callers storing `strategy` and
+/// `format` on their own. Decode with [`crate::decode_raw`].
+pub fn encode_raw(
+    reader: impl Read,
+    writer: impl Write,
+    strategy: PruningStrategy,
+    format: TokenFormat,
+) -> Result<()> {
+    encode_tokens(
+        reader,
+        WordWriter::new(BufWriter::new(writer)),
+        strategy,
+        format,
+    )
+}
 
+fn encode_tokens(
+    reader: impl Read,
+    mut writer: WordWriter<impl Write>,
+    strategy: PruningStrategy,
+    format: TokenFormat,
+) -> Result<()> {
+    let mut reader = BufReader::new(reader);
     let mut buf = vec![0u8];
-    let mut dictionary = Dictionary::new(strategy.into());
+    let mut dictionary = Dictionary::new(strategy);
+    let mut literals = AdaptiveHuffman::new(u8::BITS as u8);
 
-    let mut word_buf = Vec::new();
+    // known phrase read so far, none between tokens
+    let mut current = None;
+    let mut indices = 0;
     loop {
         let len = reader.read(&mut buf)?;
 
@@ -21,25 +62,49 @@ pub fn encode(reader: impl Read, writer: impl Write, strategy: i64) -> Result<()
             break;
         }
 
-        word_buf.push(buf[0]);
+        let prefix = match current {
+            Some(index) => index,
+            None => {
+                // dictionary may be dropped for the new token, so the index range is taken first
+                indices = index_count(&dictionary);
+                dictionary.start_token();
+                0
+            }
+        };
+
+        if let Some(index) = dictionary.child(prefix, buf[0]) {
+            current = Some(index);
+            continue;
+        }
 
-        if let Some(w) = dictionary.add(&word_buf) {
-            elias::write_gamma_elias(&mut writer, w.0 as u64)?;
-            writer.write((w.1 as u64, u8::BITS as usize))?;
-            word_buf.clear();
+        match format {
+            TokenFormat::Gamma => {
+                elias::write_gamma_elias(&mut writer, prefix as u64)?;
+                writer.write((buf[0] as u64, u8::BITS as usize))?;
+            }
+            TokenFormat::Compact => {
+                truncated::write_truncated_binary(&mut writer, prefix as u64, indices)?;
+                literals.encode_word(buf[0] as u64, &mut writer)?;
+            }
         }
+        dictionary.add(prefix, buf[0]);
+        current = None;
     }
 
-    if word_buf.len() == 0 {
-        elias::write_gamma_elias(&mut writer, dictionary.len() as u64 + 1)?;
-    } else {
-        elias::write_gamma_elias(&mut writer, dictionary.len() as u64 + 2)?;
-        let Some(index) = dictionary.find(&word_buf) else {
-            bail!("Something wrong happen");
-        };
-        elias::write_gamma_elias(&mut writer, index as u64)?;
+    let indices = index_count(&dictionary);
+    let mut write_index = |index: u64, count: u64| match format {
+        TokenFormat::Gamma => elias::write_gamma_elias(&mut writer, index),
+        TokenFormat::Compact => truncated::write_truncated_binary(&mut writer, index, count),
+    };
+
+    match current {
+        None => write_index(dictionary.len() as u64 + 1, indices)?,
+        Some(index) => {
+            write_index(dictionary.len() as u64 + 2, indices)?;
+            // phrase indices start from one, zero is left unused
+            write_index(index as u64, dictionary.len() as u64 + 1)?;
+        }
     }
 
     Ok(())
 }
-
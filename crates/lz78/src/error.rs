@@ -0,0 +1,48 @@
+use std::io;
+
+use thiserror::Error;
+
+use crate::dictionary::PruningStrategy;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Unexpected end of input")]
+    UnexpectedEof,
+    #[error("Unsupported LZ78 header version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Unknown LZ78 pruning strategy {0}")]
+    UnknownStrategy(u8),
+    #[error("LZW does not support {0:?} pruning")]
+    UnsupportedStrategy(PruningStrategy),
+    /// token refers to a phrase the dictionary does not have
+    #[error("Phrase index {0} is out of range")]
+    IndexOutOfRange(u64),
+    #[error("Not valid LZ78 encoded data: {0}")]
+    CorruptData(&'static str),
+    /// failure in literals of the compact token format
+    #[error(transparent)]
+    Huffman(huffman::Error),
+    #[error(transparent)]
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => Error::UnexpectedEof,
+            _ => Error::Io(err),
+        }
+    }
+}
+
+impl From<huffman::Error> for Error {
+    fn from(err: huffman::Error) -> Self {
+        match err {
+            huffman::Error::UnexpectedEof => Error::UnexpectedEof,
+            huffman::Error::Io(err) => Error::Io(err),
+            err => Error::Huffman(err),
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,81 @@
+//! Header of LZ78 and LZW streams: pruning strategy the dictionaries of both
+//! encoder and decoder follow. It holds the header version and strategy tag,
+//! 8 bits each, followed by the gamma coded limit.
+//!
+//! Callers that store the strategy themselves leave the header out, see
+//! [`crate::encode_raw`].
+
+use std::io::{Read, Write};
+
+use bit_utils::{elias, read::BitReader, write::WordWriter};
+
+use crate::{
+    dictionary::PruningStrategy,
+    error::{Error, Result},
+};
+
+pub const VERSION: u8 = 1;
+
+pub fn write(writer: &mut WordWriter<impl Write>, strategy: PruningStrategy) -> Result<()> {
+    writer.write((VERSION as u64, u8::BITS as usize))?;
+    writer.write((strategy.tag() as u64, u8::BITS as usize))?;
+    // gamma code can't hold the largest value, no dictionary gets that long anyway
+    elias::write_gamma_elias(writer, strategy.limit().min(u64::MAX - 1))?;
+
+    Ok(())
+}
+
+pub fn read(reader: &mut BitReader<impl Read>) -> Result<PruningStrategy> {
+    let version = reader.read(u8::BITS as usize)? as u8;
+    if version != VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+
+    let tag = reader.read(u8::BITS as usize)? as u8;
+    let limit = elias::read_gamma_elias(reader)?;
+
+    match PruningStrategy::from_tag(tag, limit) {
+        Some(strategy) => Ok(strategy),
+        None => Err(Error::UnknownStrategy(tag)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bit_utils::{read::BitReader, write::WordWriter};
+
+    use super::{read, write, VERSION};
+    use crate::{dictionary::PruningStrategy, error::Error};
+
+    #[test]
+    fn roundtrip_strategies() {
+        for strategy in [
+            PruningStrategy::Never,
+            PruningStrategy::Drop(1),
+            PruningStrategy::Freeze(4096),
+            PruningStrategy::Lru(4096),
+            PruningStrategy::Lfu(1 << 40),
+            PruningStrategy::Reset(u64::MAX - 1),
+        ] {
+            let mut buffer = Vec::new();
+            let mut writer = WordWriter::new(&mut buffer);
+            write(&mut writer, strategy).unwrap();
+            writer.flush().unwrap();
+            drop(writer);
+
+            assert_eq!(read(&mut BitReader::new(&buffer[..])).unwrap(), strategy);
+        }
+    }
+
+    #[test]
+    fn should_reject_unknown_versions_and_tags() {
+        assert!(matches!(
+            read(&mut BitReader::new(&[VERSION + 1, 0, 0x80][..])),
+            Err(Error::UnsupportedVersion(version)) if version == VERSION + 1
+        ));
+        assert!(matches!(
+            read(&mut BitReader::new(&[VERSION, 200, 0x80][..])),
+            Err(Error::UnknownStrategy(200))
+        ));
+    }
+}
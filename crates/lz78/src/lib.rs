@@ -1,6 +1,135 @@
 pub mod encode;
 pub mod decode;
 pub mod dictionary;
+pub mod error;
+pub mod header;
+pub mod lzw;
 
-pub use encode::encode;
-pub use decode::decode;
+pub use encode::{encode, encode_raw, encode_with};
+pub use decode::{decode, decode_raw, decode_with};
+pub use error::{Error, Result};
+
+use dictionary::Dictionary;
+
+/// How phrase indices and literals of LZ78 tokens are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TokenFormat {
+    /// gamma coded indices and raw 8-bit literals
+    #[default]
+    Gamma,
+    /// indices in truncated binary over all values possible at that point,
+    /// literals coded with adaptive Huffman
+    Compact,
+}
+
+/// Number of values the next phrase index may take: every phrase, the empty
+/// one, and the two markers ending the stream.
+fn index_count(dictionary: &Dictionary) -> u64 {
+    dictionary.len() as u64 + 3
+}
+
+#[cfg(test)]
+mod test {
+    use bit_utils::{elias, write::WordWriter};
+
+    use super::{
+        decode, decode_raw, decode_with, dictionary::PruningStrategy, encode_raw, encode_with,
+        header, Error, TokenFormat,
+    };
+
+    fn roundtrip(data: &[u8], strategy: PruningStrategy, format: TokenFormat) -> usize {
+        let mut compressed = Vec::new();
+        encode_with(data, &mut compressed, strategy, format).unwrap();
+        let mut output = Vec::new();
+        decode_with(format, &compressed[..], &mut output).unwrap();
+
+        assert_eq!(output, data);
+
+        compressed.len()
+    }
+
+    #[test]
+    fn roundtrip_formats() {
+        let data = b"abracadabra, the quick brown fox jumps over the lazy dog".repeat(20);
+
+        for format in [TokenFormat::Gamma, TokenFormat::Compact] {
+            for strategy in [
+                PruningStrategy::Never,
+                PruningStrategy::Drop(16),
+                PruningStrategy::Freeze(16),
+                PruningStrategy::Drop(1),
+                PruningStrategy::Freeze(1),
+                PruningStrategy::Lru(16),
+                PruningStrategy::Lfu(16),
+                PruningStrategy::Reset(16),
+                PruningStrategy::Lru(1),
+                PruningStrategy::Reset(1),
+            ] {
+                roundtrip(&[], strategy, format);
+                roundtrip(b"a", strategy, format);
+                roundtrip(b"abab", strategy, format);
+                roundtrip(&data, strategy, format);
+            }
+        }
+    }
+
+    #[test]
+    fn roundtrip_without_header() {
+        let data = b"abracadabra, the quick brown fox jumps over the lazy dog".repeat(20);
+
+        for format in [TokenFormat::Gamma, TokenFormat::Compact] {
+            for strategy in [PruningStrategy::Never, PruningStrategy::Lru(16)] {
+                let mut compressed = Vec::new();
+                encode_raw(&data[..], &mut compressed, strategy, format).unwrap();
+                let mut output = Vec::new();
+                decode_raw(strategy, format, &compressed[..], &mut output).unwrap();
+
+                assert_eq!(output, data);
+                // header is the version, tag and limit
+                assert!(compressed.len() + 2 <= roundtrip(&data, strategy, format));
+            }
+        }
+    }
+
+    #[test]
+    fn compact_format_should_be_smaller() {
+        let data = b"abracadabra, the quick brown fox jumps over the lazy dog".repeat(20);
+
+        assert!(
+            roundtrip(&data, PruningStrategy::Never, TokenFormat::Compact) * 10
+                < roundtrip(&data, PruningStrategy::Never, TokenFormat::Gamma) * 8
+        );
+    }
+
+    #[test]
+    fn should_report_malformed_input() {
+        let mut compressed = Vec::new();
+        encode_with(
+            &b"abracadabra"[..],
+            &mut compressed,
+            PruningStrategy::Never,
+            TokenFormat::Gamma,
+        )
+        .unwrap();
+        compressed.pop();
+
+        assert!(matches!(
+            decode(&compressed[..], Vec::new()),
+            Err(Error::UnexpectedEof)
+        ));
+
+        // token referring to phrase 5 of an empty dictionary
+        let mut compressed = Vec::new();
+        let mut writer = WordWriter::new(&mut compressed);
+        header::write(&mut writer, PruningStrategy::Never).unwrap();
+        elias::write_gamma_elias(&mut writer, 5).unwrap();
+        writer.write((b'a' as u64, 8)).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        assert!(matches!(
+            decode(&compressed[..], Vec::new()),
+            Err(Error::IndexOutOfRange(5))
+        ));
+    }
+}
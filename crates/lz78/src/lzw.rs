@@ -0,0 +1,296 @@
+//! LZW: dictionary starts with every single byte, so only phrase indices are
+//! written. Indices are stored in just as many bits as the largest index the
+//! decoder may expect at that point, so their width grows with the dictionary.
+
+use std::{
+    collections::HashMap,
+    io::{BufReader, BufWriter, Read, Write},
+};
+
+use bit_utils::{read::BitReader, write::WordWriter};
+
+use crate::{
+    dictionary::PruningStrategy,
+    error::{Error, Result},
+    header,
+};
+
+/// index terminating the stream
+const END: u64 = 1 << u8::BITS;
+/// index of the first learned phrase
+const FIRST_PHRASE: u64 = END + 1;
+
+struct Table {
+    /// learned phrases as (prefix index, last byte)
+    nodes: Vec<(u64, u8)>,
+    index: HashMap<(u64, u8), u64>,
+    strategy: PruningStrategy,
+}
+
+impl Table {
+    fn new(strategy: PruningStrategy) -> Self {
+        Self {
+            nodes: Vec::new(),
+            index: HashMap::new(),
+            strategy,
+        }
+    }
+
+    /// Number of indices in use, including the seeded bytes and [`END`].
+    fn len(&self) -> u64 {
+        FIRST_PHRASE + self.nodes.len() as u64
+    }
+
+    /// Applies pruning strategy before a new phrase is learned, returns `false`
+    /// when the phrase must be skipped. Dropped dictionary skips one phrase too,
+    /// as its prefix is no longer known.
+    fn reserve(&mut self) -> bool {
+        match self.strategy {
+            PruningStrategy::Never => true,
+            PruningStrategy::Drop(max_len) => {
+                if self.nodes.len() as u64 >= max_len {
+                    self.nodes.clear();
+                    self.index.clear();
+                    false
+                } else {
+                    true
+                }
+            }
+            PruningStrategy::Freeze(max_len) => (self.nodes.len() as u64) < max_len,
+            PruningStrategy::Lru(_) | PruningStrategy::Lfu(_) | PruningStrategy::Reset(_) => {
+                unreachable!("rejected by `table_strategy`")
+            }
+        }
+    }
+
+    fn push(&mut self, prefix: u64, byte: u8) {
+        self.index.insert((prefix, byte), self.len());
+        self.nodes.push((prefix, byte));
+    }
+
+    fn find(&self, prefix: u64, byte: u8) -> Option<u64> {
+        self.index.get(&(prefix, byte)).copied()
+    }
+
+    fn get(&self, mut index: u64) -> Option<Vec<u8>> {
+        let mut phrase = Vec::new();
+
+        while index >= FIRST_PHRASE {
+            let &(prefix, byte) = self.nodes.get((index - FIRST_PHRASE) as usize)?;
+            phrase.push(byte);
+            index = prefix;
+        }
+
+        if index >= END {
+            return None;
+        }
+
+        phrase.push(index as u8);
+        phrase.reverse();
+
+        Some(phrase)
+    }
+}
+
+/// Strategies replacing or dropping phrases based on usage are not supported,
+/// as the decoder learns every phrase one index late.
+fn table_strategy(strategy: PruningStrategy) -> Result<PruningStrategy> {
+    match strategy {
+        strategy @ (PruningStrategy::Never
+        | PruningStrategy::Drop(_)
+        | PruningStrategy::Freeze(_)) => Ok(strategy),
+        strategy => Err(Error::UnsupportedStrategy(strategy)),
+    }
+}
+
+/// Number of bits needed for indices up to `max_index`.
+fn index_width(max_index: u64) -> usize {
+    (u64::BITS - max_index.leading_zeros()) as usize
+}
+
+pub fn encode(reader: impl Read, writer: impl Write, strategy: PruningStrategy) -> Result<()> {
+    let mut writer = WordWriter::new(BufWriter::new(writer));
+    let table = Table::new(table_strategy(strategy)?);
+    header::write(&mut writer, strategy)?;
+
+    encode_indices(reader, writer, table)
+}
+
+/// Encodes without the stream header, for callers storing `strategy` on
+/// their own. Decode with [`decode_raw`].
+pub fn encode_raw(reader: impl Read, writer: impl Write, strategy: PruningStrategy) -> Result<()> {
+    let table = Table::new(table_strategy(strategy)?);
+
+    encode_indices(reader, WordWriter::new(BufWriter::new(writer)), table)
+}
+
+fn encode_indices(
+    reader: impl Read,
+    mut writer: WordWriter<impl Write>,
+    mut table: Table,
+) -> Result<()> {
+    let reader = BufReader::new(reader);
+    let mut current: Option<u64> = None;
+
+    for byte in reader.bytes() {
+        let byte = byte?;
+
+        let Some(prefix) = current else {
+            current = Some(byte as u64);
+            continue;
+        };
+
+        if let Some(index) = table.find(prefix, byte) {
+            current = Some(index);
+            continue;
+        }
+
+        writer.write((prefix, index_width(table.len() - 1)))?;
+
+        if table.reserve() {
+            table.push(prefix, byte);
+        }
+
+        current = Some(byte as u64);
+    }
+
+    if let Some(prefix) = current {
+        writer.write((prefix, index_width(table.len() - 1)))?;
+    }
+
+    // decoder learns the phrase of last index only when it reads the next one
+    let pending = current.is_some() && table.reserve();
+    writer.write((END, index_width(table.len() + pending as u64 - 1)))?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+pub fn decode(reader: impl Read, writer: impl Write) -> Result<()> {
+    let mut reader = BitReader::new(BufReader::new(reader));
+    let table = Table::new(table_strategy(header::read(&mut reader)?)?);
+
+    decode_indices(reader, writer, table)
+}
+
+/// Decodes a stream without the header, written by [`encode_raw`] with the
+/// same `strategy`.
+pub fn decode_raw(strategy: PruningStrategy, reader: impl Read, writer: impl Write) -> Result<()> {
+    let table = Table::new(table_strategy(strategy)?);
+
+    decode_indices(BitReader::new(BufReader::new(reader)), writer, table)
+}
+
+fn decode_indices(
+    mut reader: BitReader<impl Read>,
+    writer: impl Write,
+    mut table: Table,
+) -> Result<()> {
+    let mut writer = BufWriter::new(writer);
+    let mut previous: Option<(u64, Vec<u8>)> = None;
+
+    loop {
+        // phrase started by previous index is completed by the first byte
+        // of the current one, and may be referenced by the current index
+        let pending = previous.is_some() && table.reserve();
+        let index = reader.read(index_width(table.len() + pending as u64 - 1))?;
+
+        if index == END {
+            break;
+        }
+
+        let phrase = match (table.get(index), &previous) {
+            (Some(phrase), _) => phrase,
+            (None, Some((_, previous))) if pending && index == table.len() => {
+                [&previous[..], &previous[..1]].concat()
+            }
+            _ => return Err(Error::IndexOutOfRange(index)),
+        };
+
+        if let (true, Some((prefix, _))) = (pending, &previous) {
+            table.push(*prefix, phrase[0]);
+        }
+
+        writer.write_all(&phrase)?;
+        previous = Some((index, phrase));
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, decode_raw, encode, encode_raw, index_width};
+    use crate::{dictionary::PruningStrategy, error::Error};
+
+    fn roundtrip(data: &[u8], strategy: PruningStrategy) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        encode(data, &mut compressed, strategy).unwrap();
+        let mut output = Vec::new();
+        decode(&compressed[..], &mut output).unwrap();
+
+        assert_eq!(output, data);
+
+        compressed
+    }
+
+    #[test]
+    fn roundtrip_strategies() {
+        let data = b"TOBEORNOTTOBEORTOBEORNOT#".repeat(20);
+
+        for strategy in [
+            PruningStrategy::Never,
+            PruningStrategy::Drop(1),
+            PruningStrategy::Drop(2),
+            PruningStrategy::Drop(7),
+            PruningStrategy::Drop(300),
+            PruningStrategy::Freeze(1),
+            PruningStrategy::Freeze(5),
+            PruningStrategy::Freeze(300),
+        ] {
+            roundtrip(&[], strategy);
+            roundtrip(b"a", strategy);
+            roundtrip(b"aaaaaaaaaaaaaaa", strategy);
+            roundtrip(&data, strategy);
+        }
+    }
+
+    #[test]
+    fn roundtrip_without_header() {
+        let data = b"TOBEORNOTTOBEORTOBEORNOT#".repeat(20);
+
+        for strategy in [PruningStrategy::Never, PruningStrategy::Drop(7)] {
+            let mut compressed = Vec::new();
+            encode_raw(&data[..], &mut compressed, strategy).unwrap();
+            let mut output = Vec::new();
+            decode_raw(strategy, &compressed[..], &mut output).unwrap();
+
+            assert_eq!(output, data);
+            // header is the version, tag and limit
+            assert!(compressed.len() + 2 <= roundtrip(&data, strategy).len());
+        }
+    }
+
+    #[test]
+    fn should_reject_usage_based_strategies() {
+        assert!(matches!(
+            encode(&b"abc"[..], Vec::new(), PruningStrategy::Lru(16)),
+            Err(Error::UnsupportedStrategy(PruningStrategy::Lru(16)))
+        ));
+    }
+
+    #[test]
+    fn should_grow_index_width() {
+        assert_eq!(index_width(256), 9);
+        assert_eq!(index_width(511), 9);
+        assert_eq!(index_width(512), 10);
+
+        // header with a single bit limit, then `a`, `aa` and end index in 9 bits each
+        assert_eq!(
+            roundtrip(b"aaa", PruningStrategy::Never),
+            [1, 0, 0b10011000, 0b01100000, 0b00110000, 0b00000000]
+        );
+    }
+}
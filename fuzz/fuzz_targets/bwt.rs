@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if !data.is_empty() {
+        // first byte picks a small block size, so inputs span several blocks
+        let block_size = data[0] as u32 + 1;
+        let data = &data[1..];
+
+        let mut compressed_output = Vec::new();
+        bwt::compress(block_size, data, &mut compressed_output).unwrap();
+
+        let mut received_output = Vec::new();
+        bwt::decompress(&compressed_output[..], &mut received_output).unwrap();
+
+        assert_eq!(data, received_output);
+    };
+});
@@ -0,0 +1,19 @@
+#![no_main]
+
+use huffman::{compress_adaptive, decompress_adaptive};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if !data.is_empty() {
+        let word_size = (data[0] % 18).clamp(2, 17);
+        let mut compressed_output = Vec::new();
+
+        compress_adaptive(word_size, data, &mut compressed_output).unwrap();
+
+        let mut received_output = Vec::new();
+
+        decompress_adaptive(&compressed_output[..], &mut received_output).unwrap();
+
+        assert_eq!(data, received_output);
+    };
+});
@@ -0,0 +1,29 @@
+#![no_main]
+
+use huffman::{compress_stream, decompress_stream, Options, TreeFormat};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if !data.is_empty() {
+        let word_size = (data[0] % 18).clamp(2, 17);
+        let block_size = data[0] as usize % 64 + 1;
+        let tree_format = if data[0] & 0x80 == 0 {
+            TreeFormat::Full
+        } else {
+            TreeFormat::Canonical
+        };
+        let options = Options {
+            tree_format,
+            ..Options::new(word_size)
+        };
+        let mut compressed_output = Vec::new();
+
+        compress_stream(options, block_size, data, &mut compressed_output).unwrap();
+
+        let mut received_output = Vec::new();
+
+        decompress_stream(tree_format, &compressed_output[..], &mut received_output).unwrap();
+
+        assert_eq!(data, received_output);
+    };
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lz77::{decode, encode, Options};
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() >= 2 {
+        let mut compressed_output = Vec::new();
+
+        // first two bytes pick a small window and minimal match length
+        let options = Options::new(data[0] as u32 + 1, data[1].max(2));
+        encode(&data[2..], &mut compressed_output, options).unwrap();
+
+        let mut received_output = Vec::new();
+
+        decode(options, &compressed_output[..], &mut received_output).unwrap();
+
+        assert_eq!(&data[2..], received_output);
+    };
+});
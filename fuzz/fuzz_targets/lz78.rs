@@ -1,13 +1,13 @@
 #![no_main]
 
 use libfuzzer_sys::fuzz_target;
-use lz78::{encode, decode};
+use lz78::{decode, dictionary::PruningStrategy, encode};
 
 fuzz_target!(|data: &[u8]| {
-    if data.len() > 0 {
+    if !data.is_empty() {
         let mut compressed_output = Vec::new();
 
-        encode(data, &mut compressed_output, 0).unwrap();
+        encode(data, &mut compressed_output, PruningStrategy::Never).unwrap();
 
         let mut received_output = Vec::new();
 
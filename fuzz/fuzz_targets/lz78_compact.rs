@@ -0,0 +1,25 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lz78::{decode_with, dictionary::PruningStrategy, encode_with, TokenFormat};
+
+fuzz_target!(|data: &[u8]| {
+    if !data.is_empty() {
+        let mut compressed_output = Vec::new();
+
+        // first byte picks a small dictionary limit of either kind
+        let strategy = PruningStrategy::from(data[0] as i8 as i64);
+        encode_with(data, &mut compressed_output, strategy, TokenFormat::Compact).unwrap();
+
+        let mut received_output = Vec::new();
+
+        decode_with(
+            TokenFormat::Compact,
+            &compressed_output[..],
+            &mut received_output,
+        )
+        .unwrap();
+
+        assert_eq!(data, received_output);
+    };
+});
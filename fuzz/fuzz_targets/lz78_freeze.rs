@@ -1,13 +1,13 @@
 #![no_main]
 
 use libfuzzer_sys::fuzz_target;
-use lz78::{encode, decode};
+use lz78::{decode, dictionary::PruningStrategy, encode};
 
 fuzz_target!(|data: &[u8]| {
-    if data.len() > 0 {
+    if !data.is_empty() {
         let mut compressed_output = Vec::new();
 
-        encode(data, &mut compressed_output, -(data[0] as i64 + 1)).unwrap();
+        encode(data, &mut compressed_output, PruningStrategy::Freeze(data[0] as u64 + 1)).unwrap();
 
         let mut received_output = Vec::new();
 
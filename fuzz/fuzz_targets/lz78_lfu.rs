@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lz78::{decode, dictionary::PruningStrategy, encode};
+
+fuzz_target!(|data: &[u8]| {
+    if !data.is_empty() {
+        let strategy = PruningStrategy::Lfu(data[0] as u64 + 1);
+        let mut compressed_output = Vec::new();
+
+        encode(data, &mut compressed_output, strategy).unwrap();
+
+        let mut received_output = Vec::new();
+
+        decode(&compressed_output[..], &mut received_output).unwrap();
+
+        assert_eq!(data, received_output);
+    };
+});
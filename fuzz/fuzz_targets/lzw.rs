@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lz78::{dictionary::PruningStrategy, lzw::{encode, decode}};
+
+fuzz_target!(|data: &[u8]| {
+    if !data.is_empty() {
+        let mut compressed_output = Vec::new();
+
+        // first byte picks the pruning strategy, zero means never prune
+        encode(&data[1..], &mut compressed_output, PruningStrategy::from(data[0] as i8 as i64)).unwrap();
+
+        let mut received_output = Vec::new();
+
+        decode(&compressed_output[..], &mut received_output).unwrap();
+
+        assert_eq!(&data[1..], received_output);
+    };
+});
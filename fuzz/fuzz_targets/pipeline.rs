@@ -0,0 +1,39 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use infteor::{Codec, Pipeline};
+use libfuzzer_sys::fuzz_target;
+
+const STAGES: [Codec; 6] = [
+    Codec::Delta,
+    Codec::Lz78 {
+        strategy: lz78::dictionary::PruningStrategy::Never,
+        format: lz78::TokenFormat::Compact,
+    },
+    Codec::Lz77(lz77::Options {
+        window_size: 256,
+        min_match: 3,
+    }),
+    Codec::Bwt { block_size: 64 },
+    Codec::AdaptiveHuffman { word_size: 8 },
+    Codec::Tans { word_size: 8 },
+];
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() >= 2 {
+        // first two bytes pick the stages
+        let pipeline = Pipeline::new(STAGES[data[0] as usize % STAGES.len()])
+            .then(STAGES[data[1] as usize % STAGES.len()]);
+        let data = &data[2..];
+
+        let mut compressed_output = Vec::new();
+        infteor::compress(pipeline.clone(), Cursor::new(data), &mut compressed_output).unwrap();
+
+        let mut received_output = Vec::new();
+        let detected = infteor::decompress(&compressed_output[..], &mut received_output).unwrap();
+
+        assert_eq!(detected, pipeline);
+        assert_eq!(data, received_output);
+    };
+});
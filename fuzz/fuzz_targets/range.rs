@@ -0,0 +1,29 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if !data.is_empty() {
+        let word_size = data[0] % 16 + 2;
+        let data = &data[1..];
+
+        let mut compressed_output = Vec::new();
+        entropy::range::compress(
+            word_size,
+            Cursor::new(data),
+            &mut compressed_output,
+        )
+        .unwrap();
+        let mut received_output = Vec::new();
+        entropy::range::decompress(&compressed_output[..], &mut received_output).unwrap();
+        assert_eq!(data, received_output);
+
+        let mut compressed_output = Vec::new();
+        entropy::range::compress_adaptive(word_size, data, &mut compressed_output).unwrap();
+        let mut received_output = Vec::new();
+        entropy::range::decompress_adaptive(&compressed_output[..], &mut received_output).unwrap();
+        assert_eq!(data, received_output);
+    };
+});
@@ -1,18 +1,18 @@
 #![no_main]
 
-use std::u8;
-
 use libfuzzer_sys::fuzz_target;
-use huffman::{compress, decompress, utils::SeekableSliceReader};
+use std::io::Cursor;
+
+use huffman::{compress, decompress};
 
 fuzz_target!(|data: &[u8]| {
-    if data.len() > 0 {
+    if !data.is_empty() {
         let word_size = (data[0] % 18).clamp(2, 17);
         let mut compressed_output = Vec::new();
 
         compress(
             word_size,
-            SeekableSliceReader::new(data),
+            Cursor::new(data),
             &mut compressed_output,
         )
         .unwrap();
@@ -20,7 +20,7 @@ fuzz_target!(|data: &[u8]| {
         let mut received_output = Vec::new();
 
         decompress(
-            SeekableSliceReader::new(&compressed_output),
+            Cursor::new(&compressed_output),
             &mut received_output,
         )
         .unwrap();
@@ -0,0 +1,727 @@
+//! Archives of many files and directories, each file compressed on its own.
+//!
+//! Layout of an archive, all multi-byte values are big-endian:
+//!
+//! | size | field                         |
+//! |------|-------------------------------|
+//! | 4    | magic bytes, `INFA`           |
+//! | 1    | format version                |
+//! | n    | compressed files              |
+//! | n    | index of entries              |
+//! | 8    | offset of the index           |
+//! | 4    | magic bytes, `INFA`           |
+//!
+//! Every file is a complete container, see [`crate::container`]. Index starts
+//! with the 4-byte entry count, followed by every entry:
+//!
+//! | size | field                             |
+//! |------|-----------------------------------|
+//! | 2    | length of the path                |
+//! | n    | path, UTF-8 names joined with `/` |
+//! | 1    | kind, 0 for files, 1 for folders  |
+//! | 4    | permission bits                   |
+//! | 8    | modification time, Unix seconds   |
+//!
+//! and, for files only:
+//!
+//! | size | field                         |
+//! |------|-------------------------------|
+//! | 8    | uncompressed size             |
+//! | 4    | CRC-32 of uncompressed data   |
+//! | 8    | offset of the container       |
+//! | 8    | length of the container       |
+//! | n    | pipeline, as in the header    |
+
+use std::{
+    fs::{self, File, Metadata},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    container::{read_pipeline, write_pipeline},
+    error::{Error, Result},
+    pipeline::Pipeline,
+    Header, SummaryWriter, Trailer,
+};
+
+pub const MAGIC: [u8; 4] = *b"INFA";
+
+pub const VERSION: u8 = 1;
+
+const FILE_KIND: u8 = 0;
+const DIRECTORY_KIND: u8 = 1;
+
+/// Length of the offset and magic bytes closing the archive.
+const TRAILER_SIZE: u64 = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// relative path, names are joined with `/`
+    pub path: String,
+    pub kind: EntryKind,
+    /// Unix permission bits
+    pub mode: u32,
+    /// modification time in seconds since the Unix epoch
+    pub mtime: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    File {
+        size: u64,
+        checksum: u32,
+        pipeline: Pipeline,
+        /// position of the container in the archive
+        offset: u64,
+        compressed_size: u64,
+    },
+    Directory,
+}
+
+/// Writes files and directories into an archive, compressing every file with
+/// the same pipeline. Index is written by [`ArchiveWriter::finish`].
+pub struct ArchiveWriter<W: Write> {
+    writer: SummaryWriter<W>,
+    pipeline: Pipeline,
+    entries: Vec<Entry>,
+    /// file left out while walking directories, the archive itself usually
+    exclude: Option<PathBuf>,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    pub fn new(writer: W, pipeline: impl Into<Pipeline>) -> Result<Self> {
+        let mut writer = SummaryWriter {
+            inner: writer,
+            count: 0,
+            crc: None,
+        };
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[VERSION])?;
+
+        Ok(Self {
+            writer,
+            pipeline: pipeline.into(),
+            entries: Vec::new(),
+            exclude: None,
+        })
+    }
+
+    /// Leaves the file at `path` out, so an archive created inside one of
+    /// the added directories does not end up in itself.
+    pub fn exclude(mut self, path: &Path) -> Result<Self> {
+        self.exclude = Some(fs::canonicalize(path)?);
+
+        Ok(self)
+    }
+
+    /// Adds a file, or a directory with everything in it. Entry paths are
+    /// relative versions of the given ones, symbolic links are skipped.
+    pub fn add_path(&mut self, path: &Path) -> Result<()> {
+        let mut names = Vec::new();
+
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => match name.to_str() {
+                    Some(name) => names.push(name),
+                    None => return Err(Error::InvalidEntryPath(path.display().to_string())),
+                },
+                Component::ParentDir => {
+                    return Err(Error::InvalidEntryPath(path.display().to_string()))
+                }
+                Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+            }
+        }
+
+        self.add(path, names.join("/"))
+    }
+
+    fn add(&mut self, path: &Path, name: String) -> Result<()> {
+        let metadata = fs::symlink_metadata(path)?;
+
+        if metadata.is_dir() {
+            // root of a relative walk, such as `.`, has no entry of its own
+            if !name.is_empty() {
+                self.entries.push(Entry {
+                    path: name.clone(),
+                    kind: EntryKind::Directory,
+                    mode: mode(&metadata),
+                    mtime: mtime(&metadata)?,
+                });
+            }
+
+            let mut children = fs::read_dir(path)?
+                .map(|child| Ok(child?.file_name()))
+                .collect::<io::Result<Vec<_>>>()?;
+            children.sort();
+
+            for child in children {
+                let Some(child_name) = child.to_str() else {
+                    return Err(Error::InvalidEntryPath(
+                        path.join(&child).display().to_string(),
+                    ));
+                };
+                let child_name = match name.is_empty() {
+                    true => child_name.to_owned(),
+                    false => format!("{name}/{child_name}"),
+                };
+
+                self.add(&path.join(&child), child_name)?;
+            }
+        } else if metadata.is_file() {
+            if let Some(exclude) = &self.exclude {
+                if fs::canonicalize(path)? == *exclude {
+                    return Ok(());
+                }
+            }
+
+            self.add_file(path, name, &metadata)?;
+        }
+
+        Ok(())
+    }
+
+    fn add_file(&mut self, path: &Path, name: String, metadata: &Metadata) -> Result<()> {
+        if name.is_empty() {
+            return Err(Error::InvalidEntryPath(path.display().to_string()));
+        }
+
+        let offset = self.writer.count;
+        let trailer =
+            crate::compress_seekable(self.pipeline.clone(), File::open(path)?, &mut self.writer)?;
+
+        self.entries.push(Entry {
+            path: name,
+            kind: EntryKind::File {
+                size: trailer.size,
+                checksum: trailer.checksum,
+                pipeline: self.pipeline.clone(),
+                offset,
+                compressed_size: self.writer.count - offset,
+            },
+            mode: mode(metadata),
+            mtime: mtime(metadata)?,
+        });
+
+        Ok(())
+    }
+
+    /// Writes the index and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        let index_offset = self.writer.count;
+        let Ok(entry_count) = u32::try_from(self.entries.len()) else {
+            return Err(Error::TooManyEntries);
+        };
+
+        let mut index = Vec::new();
+        index.extend(entry_count.to_be_bytes());
+        for entry in &self.entries {
+            write_entry(&mut index, entry)?;
+        }
+
+        self.writer.write_all(&index)?;
+        self.writer.write_all(&index_offset.to_be_bytes())?;
+        self.writer.write_all(&MAGIC)?;
+        self.writer.flush()?;
+
+        Ok(self.writer.inner)
+    }
+}
+
+fn write_entry(index: &mut Vec<u8>, entry: &Entry) -> Result<()> {
+    let Ok(path_len) = u16::try_from(entry.path.len()) else {
+        return Err(Error::InvalidEntryPath(entry.path.clone()));
+    };
+
+    index.extend(path_len.to_be_bytes());
+    index.extend(entry.path.as_bytes());
+
+    match &entry.kind {
+        EntryKind::File {
+            size,
+            checksum,
+            pipeline,
+            offset,
+            compressed_size,
+        } => {
+            index.push(FILE_KIND);
+            index.extend(entry.mode.to_be_bytes());
+            index.extend(entry.mtime.to_be_bytes());
+            index.extend(size.to_be_bytes());
+            index.extend(checksum.to_be_bytes());
+            index.extend(offset.to_be_bytes());
+            index.extend(compressed_size.to_be_bytes());
+            write_pipeline(index, pipeline)?;
+        }
+        EntryKind::Directory => {
+            index.push(DIRECTORY_KIND);
+            index.extend(entry.mode.to_be_bytes());
+            index.extend(entry.mtime.to_be_bytes());
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the index of an archive and extracts its entries.
+pub struct ArchiveReader<R: Read + Seek> {
+    reader: R,
+    entries: Vec<Entry>,
+}
+
+impl<R: Read + Seek> ArchiveReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut start = [0u8; 5];
+        read_archive_bytes(&mut reader, &mut start)?;
+
+        if start[..4] != MAGIC {
+            return Err(Error::NotArchive);
+        }
+
+        if start[4] != VERSION {
+            return Err(Error::UnsupportedVersion(start[4]));
+        }
+
+        let end = reader.seek(SeekFrom::End(0))?;
+        if end < MAGIC.len() as u64 + 1 + TRAILER_SIZE {
+            return Err(Error::NotArchive);
+        }
+
+        let mut index_offset = [0u8; 8];
+        let mut magic = [0u8; 4];
+        reader.seek(SeekFrom::Start(end - TRAILER_SIZE))?;
+        read_archive_bytes(&mut reader, &mut index_offset)?;
+        read_archive_bytes(&mut reader, &mut magic)?;
+        let index_offset = u64::from_be_bytes(index_offset);
+
+        if magic != MAGIC {
+            return Err(Error::NotArchive);
+        }
+
+        if !(MAGIC.len() as u64 + 1..=end - TRAILER_SIZE).contains(&index_offset) {
+            return Err(Error::CorruptIndex("index offset is out of the archive"));
+        }
+
+        let mut index = vec![0u8; (end - TRAILER_SIZE - index_offset) as usize];
+        reader.seek(SeekFrom::Start(index_offset))?;
+        read_archive_bytes(&mut reader, &mut index)?;
+
+        let entries = read_index(&index, index_offset).map_err(|err| match err {
+            Error::TruncatedHeader => Error::CorruptIndex("index is truncated"),
+            err => err,
+        })?;
+
+        Ok(Self { reader, entries })
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Decompresses file at `entry` into `output`, checking it against the
+    /// size and, if asked to, checksum stored in the index.
+    pub fn read_file(
+        &mut self,
+        entry: &Entry,
+        verify_checksum: bool,
+        output: impl Write,
+    ) -> Result<()> {
+        let EntryKind::File {
+            size,
+            checksum,
+            offset,
+            compressed_size,
+            ..
+        } = entry.kind
+        else {
+            return Ok(());
+        };
+
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let header = Header::read(&mut (&mut self.reader).take(compressed_size))?;
+        let payload_offset = self.reader.stream_position()?;
+        let end = offset.saturating_add(compressed_size);
+
+        // trailer is read upfront, to check it before decoding
+        let trailer_offset = end.saturating_sub(Trailer::LEN as u64).max(payload_offset);
+        self.reader.seek(SeekFrom::Start(trailer_offset))?;
+        let trailer = Trailer::read(&mut (&mut self.reader).take(end - trailer_offset))?;
+        self.reader.seek(SeekFrom::Start(payload_offset))?;
+
+        if trailer.size != size || trailer.checksum != checksum {
+            return Err(Error::CorruptIndex("entry does not match its container"));
+        }
+
+        let input = BufReader::new((&mut self.reader).take(end.saturating_sub(payload_offset)));
+        crate::decode(
+            &header,
+            verify_checksum,
+            crate::block::default_threads(),
+            input,
+            output,
+        )?;
+
+        Ok(())
+    }
+
+    /// Extracts every entry under `directory`, creating folders on the way.
+    /// Paths leaving the directory are refused before anything is written.
+    pub fn extract(&mut self, directory: &Path, verify_checksum: bool) -> Result<()> {
+        let destinations = self
+            .entries
+            .iter()
+            .map(|entry| destination(directory, &entry.path))
+            .collect::<Result<Vec<_>>>()?;
+
+        let entries = self.entries.clone();
+        for (entry, destination) in entries.iter().zip(&destinations) {
+            match entry.kind {
+                EntryKind::Directory => fs::create_dir_all(destination)?,
+                EntryKind::File { .. } => {
+                    if let Some(parent) = destination.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+
+                    let mut output = BufWriter::new(File::create(destination)?);
+                    self.read_file(entry, verify_checksum, &mut output)?;
+                    let file = output.into_inner().map_err(io::Error::from)?;
+
+                    file.set_modified(system_time(entry.mtime)?)?;
+                    set_mode(destination, entry.mode)?;
+                }
+            }
+        }
+
+        // adding files changes folders, and their modes may forbid it,
+        // so they are restored last, the innermost first
+        for (entry, destination) in entries.iter().zip(&destinations).rev() {
+            if entry.kind == EntryKind::Directory {
+                open_directory(destination)?.set_modified(system_time(entry.mtime)?)?;
+                set_mode(destination, entry.mode)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn read_index(mut index: &[u8], index_offset: u64) -> Result<Vec<Entry>> {
+    let entry_count = u32::from_be_bytes(take(&mut index)?);
+    // every entry takes at least 15 bytes, which bounds the allocation
+    let mut entries = Vec::with_capacity((entry_count as usize).min(index.len() / 15));
+
+    for _ in 0..entry_count {
+        let path_len = u16::from_be_bytes(take(&mut index)?);
+        let Some((path, rest)) = index.split_at_checked(path_len as usize) else {
+            return Err(Error::CorruptIndex("index is truncated"));
+        };
+        let Ok(path) = String::from_utf8(path.to_vec()) else {
+            return Err(Error::CorruptIndex("entry path is not valid UTF-8"));
+        };
+        index = rest;
+
+        let [kind] = take(&mut index)?;
+        let mode = u32::from_be_bytes(take(&mut index)?);
+        let mtime = i64::from_be_bytes(take(&mut index)?);
+
+        let kind = match kind {
+            FILE_KIND => {
+                let size = u64::from_be_bytes(take(&mut index)?);
+                let checksum = u32::from_be_bytes(take(&mut index)?);
+                let offset = u64::from_be_bytes(take(&mut index)?);
+                let compressed_size = u64::from_be_bytes(take(&mut index)?);
+
+                if offset
+                    .checked_add(compressed_size)
+                    .is_none_or(|end| end > index_offset)
+                {
+                    return Err(Error::CorruptIndex("entry is out of the archive"));
+                }
+
+                EntryKind::File {
+                    size,
+                    checksum,
+                    pipeline: read_pipeline(&mut index)?,
+                    offset,
+                    compressed_size,
+                }
+            }
+            DIRECTORY_KIND => EntryKind::Directory,
+            _ => return Err(Error::CorruptIndex("unknown entry kind")),
+        };
+
+        entries.push(Entry {
+            path,
+            kind,
+            mode,
+            mtime,
+        });
+    }
+
+    if !index.is_empty() {
+        return Err(Error::CorruptIndex("index has trailing bytes"));
+    }
+
+    Ok(entries)
+}
+
+/// Takes the next `N` bytes of the index.
+fn take<const N: usize>(index: &mut &[u8]) -> Result<[u8; N]> {
+    let Some((bytes, rest)) = index.split_first_chunk() else {
+        return Err(Error::CorruptIndex("index is truncated"));
+    };
+    *index = rest;
+
+    Ok(*bytes)
+}
+
+fn read_archive_bytes(reader: &mut impl Read, buf: &mut [u8]) -> Result<()> {
+    match reader.read_exact(buf) {
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Err(Error::NotArchive),
+        result => Ok(result?),
+    }
+}
+
+/// Location of entry `path` under `directory`. Every name of the path has to
+/// be a plain file name, so that the entry stays inside the directory.
+fn destination(directory: &Path, path: &str) -> Result<PathBuf> {
+    let mut destination = directory.to_path_buf();
+
+    for name in path.split('/') {
+        let mut components = Path::new(name).components();
+
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) => destination.push(name),
+            _ => return Err(Error::InvalidEntryPath(path.to_owned())),
+        }
+    }
+
+    Ok(destination)
+}
+
+fn mtime(metadata: &Metadata) -> Result<i64> {
+    let mtime = match metadata.modified()?.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    };
+
+    Ok(mtime)
+}
+
+fn system_time(mtime: i64) -> Result<SystemTime> {
+    let since = Duration::from_secs(mtime.unsigned_abs());
+    let time = match mtime {
+        0.. => SystemTime::UNIX_EPOCH.checked_add(since),
+        _ => SystemTime::UNIX_EPOCH.checked_sub(since),
+    };
+
+    time.ok_or(Error::CorruptIndex("modification time is out of range"))
+}
+
+#[cfg(unix)]
+fn mode(metadata: &Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn mode(metadata: &Metadata) -> u32 {
+    match (metadata.is_dir(), metadata.permissions().readonly()) {
+        (true, _) => 0o755,
+        (false, true) => 0o444,
+        (false, false) => 0o644,
+    }
+}
+
+/// Restores permission bits, leaving out setuid, setgid and sticky ones.
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(mode & 0o200 == 0);
+    fs::set_permissions(path, permissions)?;
+
+    Ok(())
+}
+
+/// Opens a directory for changing its modification time.
+#[cfg(not(windows))]
+fn open_directory(path: &Path) -> io::Result<File> {
+    File::open(path)
+}
+
+/// Opens a directory for changing its modification time, which Windows
+/// allows only with backup semantics and access to its attributes.
+#[cfg(windows)]
+fn open_directory(path: &Path) -> io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    const FILE_WRITE_ATTRIBUTES: u32 = 0x0100;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+
+    fs::OpenOptions::new()
+        .access_mode(FILE_WRITE_ATTRIBUTES)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs,
+        io::Cursor,
+        path::{Path, PathBuf},
+        time::UNIX_EPOCH,
+    };
+
+    use crate::{container::Codec, error::Error, pipeline::Pipeline};
+
+    use super::{destination, ArchiveReader, ArchiveWriter, EntryKind};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("infteor-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    fn archive(root: &Path, pipeline: Pipeline) -> Vec<u8> {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()), pipeline).unwrap();
+        for name in ["a.txt", "nested"] {
+            writer.add_path(&root.join(name)).unwrap();
+        }
+
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn should_roundtrip_directories() {
+        let root = temp_dir("archive-roundtrip");
+        let data = b"abracadabra ".repeat(100);
+        fs::write(root.join("a.txt"), &data).unwrap();
+        fs::create_dir_all(root.join("nested/empty")).unwrap();
+        fs::write(root.join("nested/b.bin"), [0u8, 1, 2, 255]).unwrap();
+        fs::write(root.join("nested/c.txt"), []).unwrap();
+
+        let pipeline = Pipeline::new(Codec::Bwt {
+            block_size: 1 << 16,
+        })
+        .then(Codec::Huffman(huffman::Options::new(8)));
+        let bytes = archive(&root, pipeline.clone());
+
+        let mut reader = ArchiveReader::new(Cursor::new(bytes)).unwrap();
+        let paths = reader
+            .entries()
+            .iter()
+            .map(|entry| entry.path.as_str())
+            .collect::<Vec<_>>();
+        // leading folders of the given paths are kept
+        let prefix = root.strip_prefix("/").unwrap().to_str().unwrap();
+        assert_eq!(
+            paths,
+            [
+                "a.txt",
+                "nested",
+                "nested/b.bin",
+                "nested/c.txt",
+                "nested/empty"
+            ]
+            .map(|name| format!("{prefix}/{name}"))
+        );
+        assert!(matches!(
+            &reader.entries()[0].kind,
+            EntryKind::File { size: 1200, pipeline: p, .. } if *p == pipeline
+        ));
+
+        let output = root.join("output");
+        reader.extract(&output, true).unwrap();
+        let extracted = output.join(prefix);
+        assert_eq!(fs::read(extracted.join("a.txt")).unwrap(), data);
+        assert_eq!(
+            fs::read(extracted.join("nested/b.bin")).unwrap(),
+            [0, 1, 2, 255]
+        );
+        assert_eq!(fs::read(extracted.join("nested/c.txt")).unwrap(), []);
+        assert!(extracted.join("nested/empty").is_dir());
+        // modification times are stored with a precision of seconds
+        let mtime = |path: PathBuf| {
+            let modified = fs::metadata(path).unwrap().modified().unwrap();
+            modified.duration_since(UNIX_EPOCH).unwrap().as_secs()
+        };
+        assert_eq!(mtime(extracted.join("a.txt")), mtime(root.join("a.txt")));
+        assert_eq!(mtime(extracted.join("nested")), mtime(root.join("nested")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn should_detect_corrupted_archive() {
+        let root = temp_dir("archive-corrupted");
+        fs::write(root.join("a.txt"), b"hello, archive ".repeat(50)).unwrap();
+        fs::create_dir_all(root.join("nested")).unwrap();
+        let bytes = archive(&root, Codec::Huffman(huffman::Options::new(8)).into());
+
+        assert!(matches!(
+            ArchiveReader::new(Cursor::new(b"INFT\x04".to_vec())),
+            Err(Error::NotArchive)
+        ));
+        assert!(matches!(
+            ArchiveReader::new(Cursor::new(bytes[..bytes.len() - 1].to_vec())),
+            Err(Error::NotArchive)
+        ));
+
+        let mut truncated_index = bytes.clone();
+        let trailer = bytes.len() - 12;
+        let offset = (trailer - 2) as u64;
+        truncated_index[trailer..trailer + 8].copy_from_slice(&offset.to_be_bytes());
+        assert!(matches!(
+            ArchiveReader::new(Cursor::new(truncated_index)),
+            Err(Error::CorruptIndex(_))
+        ));
+
+        let mut damaged = bytes.clone();
+        damaged[40] ^= 0x10;
+        let mut reader = ArchiveReader::new(Cursor::new(damaged)).unwrap();
+        let entry = reader.entries()[0].clone();
+        assert!(reader.read_file(&entry, true, Vec::new()).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn should_keep_entries_inside_directory() {
+        let directory = Path::new("output");
+        assert_eq!(
+            destination(directory, "a/b.txt").unwrap(),
+            Path::new("output/a/b.txt")
+        );
+
+        for path in ["../x", "a/../../x", "/etc/passwd", "a//b", "./a", "a/.", ""] {
+            assert!(
+                matches!(
+                    destination(directory, path),
+                    Err(Error::InvalidEntryPath(_))
+                ),
+                "{path:?} should be rejected"
+            );
+        }
+
+        let mut writer =
+            ArchiveWriter::new(Vec::new(), Codec::Huffman(huffman::Options::new(8))).unwrap();
+        assert!(matches!(
+            writer.add_path(Path::new("../x")),
+            Err(Error::InvalidEntryPath(_))
+        ));
+    }
+}
@@ -0,0 +1,348 @@
+//! Compression of input split into independent blocks, several of them at
+//! once on separate threads. Every block goes through the whole pipeline on
+//! its own, so dictionaries and Huffman trees start afresh in each of them,
+//! trading some compression ratio for speed.
+//!
+//! Payload of a container with a block size is a sequence of blocks:
+//!
+//! | size | field                          |
+//! |------|--------------------------------|
+//! | 8    | length of the compressed block |
+//! | 4    | CRC-32 of the block            |
+//! | n    | compressed block               |
+//!
+//! ended by the length `u64::MAX`. Every block but the last one holds exactly
+//! `block_size` bytes of uncompressed data. Blocks are followed by their
+//! index, which ends right before the container trailer, so any block can be
+//! found from the end of the file without reading those before it:
+//!
+//! | size | field                                        |
+//! |------|----------------------------------------------|
+//! | 8    | offset of a block in the payload, every one  |
+//! | 8    | number of blocks                             |
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Read, Seek, SeekFrom, Write},
+    num::NonZeroUsize,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Mutex},
+    thread,
+};
+
+use crate::{
+    checksum::crc32,
+    container::{Header, Trailer},
+    error::{Error, Result},
+    pipeline::{Decoder, Encoder, Pipeline},
+};
+
+pub const DEFAULT_BLOCK_SIZE: u32 = 1 << 20;
+
+/// Length in place of the one of the next block, after the last block.
+const END: u64 = u64::MAX;
+
+/// Length of the fields in front of every compressed block.
+pub(crate) const BLOCK_HEADER_LEN: u64 = 12;
+
+/// One thread for every available core.
+pub fn default_threads() -> NonZeroUsize {
+    thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
+/// Compresses `input` in blocks of `block_size` bytes on `threads` workers.
+/// Only the blocks in flight are kept in memory, along with their offsets.
+pub(crate) fn encode(
+    pipeline: &Pipeline,
+    block_size: u32,
+    threads: NonZeroUsize,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> Result<()> {
+    let mut offsets = Vec::new();
+    let mut offset = 0;
+
+    in_pool(
+        threads,
+        |_| {
+            let mut block = Vec::new();
+            input
+                .by_ref()
+                .take(block_size as u64)
+                .read_to_end(&mut block)?;
+
+            Ok((!block.is_empty()).then_some(block))
+        },
+        |_, block| Ok((crc32(&block), pipeline.encode_to_vec(&block)?)),
+        |_, (checksum, block)| {
+            offsets.push(offset);
+            offset += BLOCK_HEADER_LEN + block.len() as u64;
+
+            output.write_all(&(block.len() as u64).to_be_bytes())?;
+            output.write_all(&checksum.to_be_bytes())?;
+            output.write_all(&block)?;
+
+            Ok(())
+        },
+    )?;
+
+    output.write_all(&END.to_be_bytes())?;
+    for offset in offsets.iter().chain(&[offsets.len() as u64]) {
+        output.write_all(&offset.to_be_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Decompresses blocks of `block_size` bytes on `threads` workers, checking
+/// the size of each of them and, if `verify_checksums` is set, their
+/// checksums.
+pub(crate) fn decode(
+    decoder: &(dyn Decoder + Sync),
+    block_size: u32,
+    verify_checksums: bool,
+    threads: NonZeroUsize,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> Result<()> {
+    let block_size = block_size as u64;
+    // length of a block shorter than `block_size`
+    let mut short = None;
+    let mut offsets = Vec::new();
+    let mut offset = 0;
+
+    in_pool(
+        threads,
+        |index| {
+            let Some((checksum, block)) = read_checked_block(input, index)? else {
+                return Ok(None);
+            };
+            offsets.push(offset);
+            offset += BLOCK_HEADER_LEN + block.len() as u64;
+
+            Ok(Some((checksum, block)))
+        },
+        |index, (expected, block)| {
+            let mut output = Vec::new();
+            decoder.decode(&mut &block[..], &mut output)?;
+
+            if verify_checksums {
+                let actual = crc32(&output);
+
+                if actual != expected {
+                    return Err(Error::BlockChecksumMismatch {
+                        index,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+
+            Ok(output)
+        },
+        |_, block| {
+            let len = block.len() as u64;
+
+            // only the last block may be shorter, so a short one must not be followed
+            if let Some(actual) = short.or((len > block_size).then_some(len)) {
+                return Err(Error::SizeMismatch {
+                    expected: block_size,
+                    actual,
+                });
+            }
+            if len < block_size {
+                short = Some(len);
+            }
+
+            Ok(output.write_all(&block)?)
+        },
+    )?;
+
+    // index is not needed when reading blocks in order, but must list them
+    for offset in offsets.iter().chain(&[offsets.len() as u64]) {
+        if read_index_entry(input)? != *offset {
+            return Err(Error::CorruptBlockIndex);
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes block number `index` alone, found through the index at the end of
+/// `input`, which holds a single container from the current position on.
+pub(crate) fn decode_block(
+    input: &mut (impl Read + Seek),
+    index: u64,
+    output: &mut impl Write,
+) -> Result<()> {
+    let header = Header::read(input)?;
+    let Some(block_size) = header.block_size else {
+        return Err(Error::MissingBlockIndex);
+    };
+
+    let payload = input.stream_position()?;
+    let end = input.seek(SeekFrom::End(0))?;
+    let Some(count_offset) = end
+        .checked_sub(Trailer::LEN as u64 + 8)
+        .filter(|&offset| offset >= payload)
+    else {
+        return Err(Error::CorruptBlockIndex);
+    };
+
+    input.seek(SeekFrom::Start(count_offset))?;
+    let count = read_index_entry(input)?;
+    if index >= count {
+        return Err(Error::BlockOutOfRange { index, count });
+    }
+
+    let Some(entry_offset) = (count - index)
+        .checked_mul(8)
+        .and_then(|len| count_offset.checked_sub(len))
+        .filter(|&offset| offset >= payload)
+    else {
+        return Err(Error::CorruptBlockIndex);
+    };
+
+    input.seek(SeekFrom::Start(entry_offset))?;
+    let offset = read_index_entry(input)?;
+    input.seek(SeekFrom::Start(payload.saturating_add(offset)))?;
+
+    let Some((expected, block)) = read_checked_block(input, index)? else {
+        return Err(Error::CorruptBlockIndex);
+    };
+
+    let mut decoded = Vec::new();
+    header.pipeline.decode(&mut &block[..], &mut decoded)?;
+
+    let actual = crc32(&decoded);
+    if actual != expected {
+        return Err(Error::BlockChecksumMismatch {
+            index,
+            expected,
+            actual,
+        });
+    }
+
+    if decoded.len() as u64 > block_size as u64 {
+        return Err(Error::SizeMismatch {
+            expected: block_size as u64,
+            actual: decoded.len() as u64,
+        });
+    }
+
+    Ok(output.write_all(&decoded)?)
+}
+
+/// Reads compressed block number `index` along with its length and checksum,
+/// none after the last block.
+fn read_checked_block(input: &mut impl Read, index: u64) -> Result<Option<(u32, Vec<u8>)>> {
+    let mut length = [0u8; 8];
+    read_block_bytes(input, &mut length, index)?;
+
+    if u64::from_be_bytes(length) == END {
+        return Ok(None);
+    }
+
+    let mut checksum = [0u8; 4];
+    read_block_bytes(input, &mut checksum, index)?;
+    let block = read_block_data(input, u64::from_be_bytes(length), index)?;
+
+    Ok(Some((u32::from_be_bytes(checksum), block)))
+}
+
+fn read_block_data(input: &mut impl Read, length: u64, index: u64) -> Result<Vec<u8>> {
+    // grows along with the data read, so a damaged length allocates no more
+    let mut block = Vec::new();
+    input.take(length).read_to_end(&mut block)?;
+
+    if block.len() as u64 != length {
+        return Err(Error::TruncatedBlock(index));
+    }
+
+    Ok(block)
+}
+
+fn read_index_entry(input: &mut impl Read) -> Result<u64> {
+    let mut entry = [0u8; 8];
+
+    match input.read_exact(&mut entry) {
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Err(Error::CorruptBlockIndex),
+        result => Ok(result.map(|_| u64::from_be_bytes(entry))?),
+    }
+}
+
+fn read_block_bytes(input: &mut impl Read, buf: &mut [u8], index: u64) -> Result<()> {
+    match input.read_exact(buf) {
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Err(Error::TruncatedBlock(index)),
+        result => Ok(result?),
+    }
+}
+
+/// Applies `f` to the items returned by `next`, until it returns none, on a
+/// pool of `threads` workers. Results are passed to `write` in the order of
+/// their items. Items are read and results written on the calling thread,
+/// meanwhile the workers go on with the items in flight, at most two for
+/// every worker.
+fn in_pool<T: Send, U: Send>(
+    threads: NonZeroUsize,
+    mut next: impl FnMut(u64) -> Result<Option<T>>,
+    f: impl Fn(u64, T) -> Result<U> + Sync,
+    mut write: impl FnMut(u64, U) -> Result<()>,
+) -> Result<()> {
+    let window = 2 * threads.get() as u64;
+    let (items, queue) = mpsc::sync_channel(threads.get());
+    let queue = Mutex::new(queue);
+    let (sender, results) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..threads.get() {
+            let (queue, f, sender) = (&queue, &f, sender.clone());
+
+            scope.spawn(move || loop {
+                // lock is released before the item is processed
+                let item = queue.lock().unwrap().recv();
+                let Ok((index, item)) = item else {
+                    return;
+                };
+
+                let result = panic::catch_unwind(AssertUnwindSafe(|| f(index, item)));
+                if sender.send((index, result)).is_err() {
+                    return;
+                }
+            });
+        }
+
+        // workers stop once the items are dropped, on error as well
+        let items = items;
+        let mut pending = BTreeMap::new();
+        let (mut sent, mut written) = (0, 0);
+        let mut exhausted = false;
+
+        loop {
+            if !exhausted && sent < written + window {
+                match next(sent)? {
+                    Some(item) => {
+                        items.send((sent, item)).unwrap();
+                        sent += 1;
+                    }
+                    None => exhausted = true,
+                }
+
+                continue;
+            }
+
+            if written == sent {
+                return Ok(());
+            }
+
+            let (index, result) = results.recv().unwrap();
+            pending.insert(index, result);
+
+            while let Some(result) = pending.remove(&written) {
+                let result = result.unwrap_or_else(|err| panic::resume_unwind(err));
+                write(written, result?)?;
+                written += 1;
+            }
+        }
+    })
+}
@@ -0,0 +1,106 @@
+//! CRC-32 of uncompressed data, recorded in the container trailer. Codecs
+//! decode most damaged payloads into wrong data without noticing, the
+//! checksum catches that.
+
+use std::io::{self, Write};
+
+/// Remainders of every byte value, for the reflected polynomial.
+const TABLE: [u32; 256] = table(0xEDB8_8320);
+
+const fn table(polynomial: u32) -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut byte = 0;
+
+    while byte < table.len() {
+        let mut remainder = byte as u32;
+        let mut bit = 0;
+
+        while bit < u8::BITS {
+            remainder = match remainder & 1 {
+                0 => remainder >> 1,
+                _ => (remainder >> 1) ^ polynomial,
+            };
+            bit += 1;
+        }
+
+        table[byte] = remainder;
+        byte += 1;
+    }
+
+    table
+}
+
+/// CRC-32 as used by zlib, gzip and PNG. Data is written into it, so
+/// [`std::io::copy`] and writers teeing into it work as well.
+#[derive(Debug, Clone)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Self { state: u32::MAX }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.state = TABLE[((self.state ^ byte as u32) & 0xff) as usize] ^ (self.state >> 8);
+        }
+    }
+
+    /// Checksum of all the data so far.
+    pub fn finish(&self) -> u32 {
+        !self.state
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write for Crc32 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+
+    crc.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crc32, Crc32};
+
+    #[test]
+    fn should_match_reference_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414F_A339
+        );
+    }
+
+    #[test]
+    fn should_not_depend_on_chunking() {
+        let data = (0..1000u32).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+        let mut crc = Crc32::new();
+        for chunk in data.chunks(13) {
+            crc.update(chunk);
+        }
+
+        assert_eq!(crc.finish(), crc32(&data));
+    }
+}
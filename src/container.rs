@@ -0,0 +1,755 @@
+//! Self-describing container wrapped around the output of every codec.
+//!
+//! Layout of the header, all multi-byte values are big-endian:
+//!
+//! | size | field                         |
+//! |------|-------------------------------|
+//! | 4    | magic bytes, `INFT`           |
+//! | 1    | format version                |
+//! | 1    | number of pipeline stages     |
+//!
+//! followed by every stage, in the order they are applied when compressing:
+//!
+//! | size | field                         |
+//! |------|-------------------------------|
+//! | 1    | codec id                      |
+//! | 1    | length of codec parameters    |
+//! | n    | codec parameters              |
+//!
+//! and then by the block size:
+//!
+//! | size | field                         |
+//! |------|-------------------------------|
+//! | 4    | block size, 0 for none        |
+//!
+//! Codec payload follows the header unchanged, or split into blocks if the
+//! block size is set, see [`crate::block`]. Payload is followed by a summary
+//! of uncompressed data, the [`Trailer`], so compression reads its input
+//! only once:
+//!
+//! | size | field                         |
+//! |------|-------------------------------|
+//! | 8    | uncompressed size in bytes    |
+//! | 4    | CRC-32 of uncompressed data   |
+//!
+//! Codec parameters are stored only here, so LZ78 and LZW stages leave out
+//! the stream header their crate writes otherwise.
+
+use huffman::TreeFormat;
+use lz78::dictionary::PruningStrategy;
+use std::io::{self, Read, Write};
+
+use crate::{
+    error::{Error, Result},
+    pipeline::Pipeline,
+};
+
+pub const MAGIC: [u8; 4] = *b"INFT";
+
+pub const VERSION: u8 = 1;
+
+const HUFFMAN_ID: u8 = 1;
+const LZ78_ID: u8 = 2;
+const HUFFMAN_STREAM_ID: u8 = 3;
+const ADAPTIVE_HUFFMAN_ID: u8 = 4;
+const LZW_ID: u8 = 5;
+const LZ77_ID: u8 = 6;
+const RANGE_ID: u8 = 7;
+const ADAPTIVE_RANGE_ID: u8 = 8;
+const TANS_ID: u8 = 9;
+const ADAPTIVE_RANS_ID: u8 = 10;
+const BWT_ID: u8 = 11;
+const DELTA_ID: u8 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// static two-pass Huffman coding
+    Huffman(huffman::Options),
+    /// LZ78 with dictionary pruning strategy
+    Lz78 {
+        strategy: lz78::dictionary::PruningStrategy,
+        format: lz78::TokenFormat,
+    },
+    /// single-pass Huffman coding, building a separate tree for every block
+    HuffmanStream {
+        options: huffman::Options,
+        block_size: u32,
+    },
+    /// single-pass adaptive (FGK) Huffman coding, without a stored tree
+    AdaptiveHuffman { word_size: u8 },
+    /// LZW with dictionary pruning strategy
+    Lzw {
+        strategy: lz78::dictionary::PruningStrategy,
+    },
+    /// LZSS with a sliding window
+    Lz77(lz77::Options),
+    /// range coding with a static model stored in front of the data
+    Range { word_size: u8 },
+    /// single-pass range coding with an adaptive model
+    AdaptiveRange { word_size: u8 },
+    /// table-driven ANS with a static frequency table stored in front of the data
+    Tans { word_size: u8 },
+    /// single-pass rANS with periodically renormalized word counts
+    AdaptiveRans { word_size: u8 },
+    /// Burrows-Wheeler transform, move-to-front and zero run-length coding
+    /// followed by Huffman coding, in blocks of `block_size` bytes
+    Bwt { block_size: u32 },
+    /// differences of consecutive bytes, see [`crate::delta`]
+    Delta,
+}
+
+impl Codec {
+    pub fn id(&self) -> u8 {
+        match self {
+            Codec::Huffman(_) => HUFFMAN_ID,
+            Codec::Lz78 { .. } => LZ78_ID,
+            Codec::HuffmanStream { .. } => HUFFMAN_STREAM_ID,
+            Codec::AdaptiveHuffman { .. } => ADAPTIVE_HUFFMAN_ID,
+            Codec::Lzw { .. } => LZW_ID,
+            Codec::Lz77(_) => LZ77_ID,
+            Codec::Range { .. } => RANGE_ID,
+            Codec::AdaptiveRange { .. } => ADAPTIVE_RANGE_ID,
+            Codec::Tans { .. } => TANS_ID,
+            Codec::AdaptiveRans { .. } => ADAPTIVE_RANS_ID,
+            Codec::Bwt { .. } => BWT_ID,
+            Codec::Delta => DELTA_ID,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Codec::Huffman(_) => "huffman",
+            Codec::Lz78 { .. } => "lz78",
+            Codec::HuffmanStream { .. } => "huffman-stream",
+            Codec::AdaptiveHuffman { .. } => "adaptive-huffman",
+            Codec::Lzw { .. } => "lzw",
+            Codec::Lz77(_) => "lz77",
+            Codec::Range { .. } => "range",
+            Codec::AdaptiveRange { .. } => "adaptive-range",
+            Codec::Tans { .. } => "tans",
+            Codec::AdaptiveRans { .. } => "adaptive-rans",
+            Codec::Bwt { .. } => "bwt",
+            Codec::Delta => "delta",
+        }
+    }
+
+    fn params(&self) -> Vec<u8> {
+        match self {
+            Codec::Huffman(options) => huffman_params(options).to_vec(),
+            Codec::Lz78 { strategy, format } => {
+                let format = match format {
+                    lz78::TokenFormat::Gamma => 0,
+                    lz78::TokenFormat::Compact => 1,
+                };
+
+                [&[format][..], &strategy_params(strategy)].concat()
+            }
+            Codec::Lzw { strategy } => strategy_params(strategy).to_vec(),
+            Codec::HuffmanStream {
+                options,
+                block_size,
+            } => [&block_size.to_be_bytes()[..], &huffman_params(options)].concat(),
+            Codec::AdaptiveHuffman { word_size }
+            | Codec::Range { word_size }
+            | Codec::AdaptiveRange { word_size }
+            | Codec::Tans { word_size }
+            | Codec::AdaptiveRans { word_size } => vec![*word_size],
+            Codec::Lz77(options) => {
+                [&options.window_size.to_be_bytes()[..], &[options.min_match]].concat()
+            }
+            Codec::Bwt { block_size } => block_size.to_be_bytes().to_vec(),
+            Codec::Delta => vec![],
+        }
+    }
+
+    fn from_params(id: u8, params: &[u8]) -> Result<Self> {
+        match id {
+            HUFFMAN_ID => Ok(Codec::Huffman(huffman_options(params)?)),
+            LZ78_ID => {
+                let Some((&format, params)) = params.split_first() else {
+                    return Err(Error::InvalidParameters("lz78"));
+                };
+
+                Ok(Codec::Lz78 {
+                    strategy: strategy(params, "lz78")?,
+                    format: match format {
+                        0 => lz78::TokenFormat::Gamma,
+                        1 => lz78::TokenFormat::Compact,
+                        _ => return Err(Error::InvalidParameters("lz78")),
+                    },
+                })
+            }
+            HUFFMAN_STREAM_ID => {
+                let Some((block_size, params)) = params.split_first_chunk() else {
+                    return Err(Error::InvalidParameters("huffman-stream"));
+                };
+
+                let block_size = u32::from_be_bytes(*block_size);
+                huffman::validate_block_size(block_size as usize)?;
+
+                Ok(Codec::HuffmanStream {
+                    options: huffman_options(params)?,
+                    block_size,
+                })
+            }
+            ADAPTIVE_HUFFMAN_ID => Ok(Codec::AdaptiveHuffman {
+                word_size: word_size(params, "adaptive-huffman")?,
+            }),
+            LZW_ID => Ok(Codec::Lzw {
+                strategy: strategy(params, "lzw")?,
+            }),
+            LZ77_ID => {
+                let Some((window_size, &[min_match])) = params.split_first_chunk() else {
+                    return Err(Error::InvalidParameters("lz77"));
+                };
+                let options = lz77::Options::new(u32::from_be_bytes(*window_size), min_match);
+                options.validate()?;
+
+                Ok(Codec::Lz77(options))
+            }
+            RANGE_ID => Ok(Codec::Range {
+                word_size: word_size(params, "range")?,
+            }),
+            ADAPTIVE_RANGE_ID => Ok(Codec::AdaptiveRange {
+                word_size: word_size(params, "adaptive-range")?,
+            }),
+            TANS_ID => Ok(Codec::Tans {
+                word_size: word_size(params, "tans")?,
+            }),
+            ADAPTIVE_RANS_ID => Ok(Codec::AdaptiveRans {
+                word_size: word_size(params, "adaptive-rans")?,
+            }),
+            BWT_ID => {
+                let Ok(block_size) = params.try_into() else {
+                    return Err(Error::InvalidParameters("bwt"));
+                };
+                let block_size = u32::from_be_bytes(block_size);
+                bwt::validate_block_size(block_size)?;
+
+                Ok(Codec::Bwt { block_size })
+            }
+            DELTA_ID => match params {
+                [] => Ok(Codec::Delta),
+                _ => Err(Error::InvalidParameters("delta")),
+            },
+            _ => Err(Error::UnknownCodec(id)),
+        }
+    }
+}
+
+fn word_size(params: &[u8], name: &'static str) -> Result<u8> {
+    let &[word_size] = params else {
+        return Err(Error::InvalidParameters(name));
+    };
+
+    check_word_size(word_size)?;
+
+    Ok(word_size)
+}
+
+fn strategy_params(strategy: &PruningStrategy) -> [u8; 9] {
+    let mut params = [0; 9];
+    params[0] = strategy.tag();
+    params[1..].copy_from_slice(&strategy.limit().to_be_bytes());
+
+    params
+}
+
+fn strategy(params: &[u8], name: &'static str) -> Result<PruningStrategy> {
+    let Some((&tag, limit)) = params.split_first() else {
+        return Err(Error::InvalidParameters(name));
+    };
+    let Ok(limit) = limit.try_into() else {
+        return Err(Error::InvalidParameters(name));
+    };
+
+    match PruningStrategy::from_tag(tag, u64::from_be_bytes(limit)) {
+        Some(strategy) => Ok(strategy),
+        None => Err(Error::InvalidParameters(name)),
+    }
+}
+
+fn huffman_params(options: &huffman::Options) -> [u8; 3] {
+    let tree_format = match options.tree_format {
+        TreeFormat::Full => 0,
+        TreeFormat::Canonical => 1,
+    };
+
+    [options.word_size, tree_format, options.max_code_length]
+}
+
+fn huffman_options(params: &[u8]) -> Result<huffman::Options> {
+    let &[word_size, tree_format, max_code_length] = params else {
+        return Err(Error::InvalidParameters("huffman"));
+    };
+
+    check_word_size(word_size)?;
+
+    // same range as the tree builder accepts, checked before any data is read
+    if !(1..=u64::BITS as u8).contains(&max_code_length) {
+        return Err(Error::InvalidParameters("huffman"));
+    }
+
+    let tree_format = match tree_format {
+        0 => TreeFormat::Full,
+        1 => TreeFormat::Canonical,
+        _ => return Err(Error::InvalidParameters("huffman")),
+    };
+
+    Ok(huffman::Options {
+        word_size,
+        tree_format,
+        max_code_length,
+    })
+}
+
+fn check_word_size(word_size: u8) -> Result<()> {
+    if !(2..=17).contains(&word_size) {
+        return Err(Error::UnsupportedWordSize(word_size));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub pipeline: Pipeline,
+    /// size of independently compressed blocks, none if the payload is a
+    /// single stream
+    pub block_size: Option<u32>,
+}
+
+impl Header {
+    pub fn new(pipeline: impl Into<Pipeline>) -> Self {
+        Self {
+            pipeline: pipeline.into(),
+            block_size: None,
+        }
+    }
+
+    /// Writes the header, size and checksum of uncompressed data follow the
+    /// payload in a [`Trailer`].
+    pub fn write(&self, writer: &mut impl Write) -> Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        write_pipeline(writer, &self.pipeline)?;
+        writer.write_all(&self.block_size.unwrap_or(0).to_be_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn read(reader: &mut impl Read) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        read_header_bytes(reader, &mut magic)?;
+
+        if magic != MAGIC {
+            return Err(Error::NotInfteorFile);
+        }
+
+        let mut version = [0u8];
+        read_header_bytes(reader, &mut version)?;
+
+        if version[0] != VERSION {
+            return Err(Error::UnsupportedVersion(version[0]));
+        }
+
+        let pipeline = read_pipeline(reader)?;
+
+        let mut block_size = [0u8; 4];
+        read_header_bytes(reader, &mut block_size)?;
+        let block_size = Some(u32::from_be_bytes(block_size)).filter(|&block_size| block_size > 0);
+
+        Ok(Self {
+            pipeline,
+            block_size,
+        })
+    }
+}
+
+/// Size and CRC-32 of uncompressed data, which follow the payload as they are
+/// known only once it is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trailer {
+    pub size: u64,
+    pub checksum: u32,
+}
+
+impl Trailer {
+    pub const LEN: usize = 12;
+
+    pub fn write(&self, writer: &mut impl Write) -> Result<()> {
+        writer.write_all(&self.size.to_be_bytes())?;
+        writer.write_all(&self.checksum.to_be_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn read(reader: &mut impl Read) -> Result<Self> {
+        let mut size = [0u8; 8];
+        let mut checksum = [0u8; 4];
+
+        match reader
+            .read_exact(&mut size)
+            .and_then(|_| reader.read_exact(&mut checksum))
+        {
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                return Err(Error::TruncatedTrailer)
+            }
+            result => result?,
+        }
+
+        Ok(Self {
+            size: u64::from_be_bytes(size),
+            checksum: u32::from_be_bytes(checksum),
+        })
+    }
+}
+
+/// Passes the payload through, holding back the last [`Trailer::LEN`] bytes
+/// of input, which end up being the trailer.
+pub(crate) struct PayloadReader<R> {
+    inner: R,
+    tail: [u8; Trailer::LEN],
+    tail_len: usize,
+}
+
+impl<R: Read> PayloadReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            tail: [0; Trailer::LEN],
+            tail_len: 0,
+        }
+    }
+
+    /// Skips what the codecs left of the payload and reads the trailer.
+    pub(crate) fn finish(mut self) -> Result<Trailer> {
+        io::copy(&mut self, &mut io::sink())?;
+
+        Trailer::read(&mut &self.tail[..self.tail_len])
+    }
+}
+
+impl<R: Read> Read for PayloadReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.tail_len < Trailer::LEN {
+            match self.inner.read(&mut self.tail[self.tail_len..])? {
+                0 => return Ok(0),
+                len => self.tail_len += len,
+            }
+        }
+
+        let len = self.inner.read(buf)?;
+        let held = Trailer::LEN;
+
+        // held back bytes come first, the last ones read are held back instead
+        if len >= held {
+            let tail = buf[len - held..len].try_into().unwrap();
+            buf.copy_within(..len - held, held);
+            buf[..held].copy_from_slice(&std::mem::replace(&mut self.tail, tail));
+        } else {
+            let mut read = [0; Trailer::LEN];
+            read[..len].copy_from_slice(&buf[..len]);
+            buf[..len].copy_from_slice(&self.tail[..len]);
+            self.tail.copy_within(len.., 0);
+            self.tail[held - len..].copy_from_slice(&read[..len]);
+        }
+
+        Ok(len)
+    }
+}
+
+/// Writes the stage count followed by every stage, as in the header.
+pub(crate) fn write_pipeline(writer: &mut impl Write, pipeline: &Pipeline) -> Result<()> {
+    let stages = pipeline.stages();
+
+    if stages.len() > Pipeline::MAX_STAGES {
+        return Err(Error::InvalidStageCount(stages.len()));
+    }
+
+    writer.write_all(&[stages.len() as u8])?;
+
+    for codec in stages {
+        let params = codec.params();
+
+        writer.write_all(&[codec.id(), params.len() as u8])?;
+        writer.write_all(&params)?;
+    }
+
+    Ok(())
+}
+
+/// Reads output of [`write_pipeline`].
+pub(crate) fn read_pipeline(reader: &mut impl Read) -> Result<Pipeline> {
+    let mut stage_count = [0u8];
+    read_header_bytes(reader, &mut stage_count)?;
+
+    let stages = (0..stage_count[0])
+        .map(|_| read_codec(reader))
+        .collect::<Result<Vec<_>>>()?;
+
+    Pipeline::from_stages(stages)
+}
+
+fn read_codec(reader: &mut impl Read) -> Result<Codec> {
+    let mut fields = [0u8; 2];
+    read_header_bytes(reader, &mut fields)?;
+    let [id, params_len] = fields;
+
+    let mut params = vec![0u8; params_len as usize];
+    read_header_bytes(reader, &mut params)?;
+
+    Codec::from_params(id, &params)
+}
+
+fn read_header_bytes(reader: &mut impl Read, buf: &mut [u8]) -> Result<()> {
+    match reader.read_exact(buf) {
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Err(Error::TruncatedHeader),
+        result => Ok(result?),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+
+    use huffman::TreeFormat;
+    use lz78::dictionary::PruningStrategy;
+
+    use super::{Codec, Header, PayloadReader, Trailer, MAGIC, VERSION};
+    use crate::{error::Error, pipeline::Pipeline};
+
+    #[test]
+    fn header_roundtrip() {
+        for codec in [
+            Codec::Huffman(huffman::Options::new(13)),
+            Codec::Lz78 {
+                strategy: PruningStrategy::Freeze(4096),
+                format: lz78::TokenFormat::Gamma,
+            },
+            Codec::Lz78 {
+                strategy: PruningStrategy::Never,
+                format: lz78::TokenFormat::Compact,
+            },
+            Codec::Lz78 {
+                strategy: PruningStrategy::Lfu(u64::MAX),
+                format: lz78::TokenFormat::Compact,
+            },
+            Codec::HuffmanStream {
+                options: huffman::Options {
+                    tree_format: TreeFormat::Canonical,
+                    max_code_length: 20,
+                    ..huffman::Options::new(8)
+                },
+                block_size: 1 << 20,
+            },
+            Codec::AdaptiveHuffman { word_size: 17 },
+            Codec::Lzw {
+                strategy: PruningStrategy::Drop(4096),
+            },
+            Codec::Lz77(lz77::Options::new(1 << 20, 4)),
+            Codec::Range { word_size: 8 },
+            Codec::AdaptiveRange { word_size: 2 },
+            Codec::Tans { word_size: 17 },
+            Codec::AdaptiveRans { word_size: 8 },
+            Codec::Bwt {
+                block_size: 900_000,
+            },
+            Codec::Delta,
+        ] {
+            let mut buffer = Vec::new();
+            Header::new(codec).write(&mut buffer).unwrap();
+
+            let header = Header::read(&mut &buffer[..]).unwrap();
+            assert_eq!(header, Header::new(codec));
+            assert_eq!(header.block_size, None);
+        }
+    }
+
+    #[test]
+    fn should_record_pipeline_stages() {
+        let pipeline = Pipeline::new(Codec::Delta)
+            .then(Codec::Lz78 {
+                strategy: PruningStrategy::Reset(256),
+                format: lz78::TokenFormat::Compact,
+            })
+            .then(Codec::AdaptiveHuffman { word_size: 8 });
+
+        let mut buffer = Vec::new();
+        Header::new(pipeline.clone()).write(&mut buffer).unwrap();
+
+        let (stages, block_size) = buffer[4..].split_at(buffer.len() - 8);
+        assert_eq!(
+            stages,
+            [VERSION, 3, 12, 0, 2, 10, 1, 5, 0, 0, 0, 0, 0, 0, 1, 0, 4, 1, 8]
+        );
+        assert_eq!(block_size, [0, 0, 0, 0]);
+        assert_eq!(Header::read(&mut &buffer[..]).unwrap().pipeline, pipeline);
+    }
+
+    #[test]
+    fn should_correctly_write_header() {
+        let mut buffer = Vec::new();
+        Header::new(Codec::Huffman(huffman::Options::new(8)))
+            .write(&mut buffer)
+            .unwrap();
+
+        assert_eq!(
+            buffer,
+            vec![b'I', b'N', b'F', b'T', VERSION, 1, 1, 3, 8, 0, 64, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn trailer_roundtrip() {
+        let trailer = Trailer {
+            size: 258,
+            checksum: 0x0102_0304,
+        };
+        let mut buffer = Vec::new();
+        trailer.write(&mut buffer).unwrap();
+
+        assert_eq!(buffer, [0, 0, 0, 0, 0, 0, 1, 2, 1, 2, 3, 4]);
+        assert_eq!(Trailer::read(&mut &buffer[..]).unwrap(), trailer);
+        assert!(matches!(
+            Trailer::read(&mut &buffer[1..]),
+            Err(Error::TruncatedTrailer)
+        ));
+    }
+
+    #[test]
+    fn payload_reader_should_hold_back_trailer() {
+        let data = (0..100).collect::<Vec<u8>>();
+
+        for chunk in [1, 5, 12, 13, 64] {
+            let mut reader = PayloadReader::new(&data[..]);
+            let mut payload = Vec::<u8>::new();
+            let mut buf = vec![0; chunk];
+
+            loop {
+                match reader.read(&mut buf).unwrap() {
+                    0 => break,
+                    len => payload.extend(&buf[..len]),
+                }
+            }
+
+            assert_eq!(payload, data[..88]);
+            assert_eq!(
+                reader.finish().unwrap(),
+                Trailer::read(&mut &data[88..]).unwrap()
+            );
+        }
+
+        assert!(matches!(
+            PayloadReader::new(&data[..5]).finish(),
+            Err(Error::TruncatedTrailer)
+        ));
+    }
+
+    #[test]
+    fn should_record_block_size() {
+        let mut buffer = Vec::new();
+        Header {
+            block_size: Some(1 << 16),
+            ..Header::new(Codec::Delta)
+        }
+        .write(&mut buffer)
+        .unwrap();
+
+        assert_eq!(buffer[buffer.len() - 4..], [0, 1, 0, 0]);
+        assert_eq!(
+            Header::read(&mut &buffer[..]).unwrap().block_size,
+            Some(1 << 16)
+        );
+    }
+
+    #[test]
+    fn should_reject_foreign_file() {
+        assert!(matches!(
+            Header::read(&mut &b"\x1f\x8b\x08\x00\x00\x00\x00\x00"[..]),
+            Err(Error::NotInfteorFile)
+        ));
+        assert!(matches!(
+            Header::read(&mut &b"IN"[..]),
+            Err(Error::TruncatedHeader)
+        ));
+    }
+
+    #[test]
+    fn should_reject_unknown_version_and_codec() {
+        let mut buffer = MAGIC.to_vec();
+        buffer.extend([VERSION + 1, 1, 1, 8]);
+        assert!(matches!(
+            Header::read(&mut &buffer[..]),
+            Err(Error::UnsupportedVersion(version)) if version == VERSION + 1
+        ));
+
+        let mut buffer = MAGIC.to_vec();
+        buffer.extend([VERSION, 1, 200, 0]);
+        assert!(matches!(
+            Header::read(&mut &buffer[..]),
+            Err(Error::UnknownCodec(200))
+        ));
+
+        let mut buffer = MAGIC.to_vec();
+        buffer.extend([VERSION, 0]);
+        buffer.extend([0; 16]);
+        assert!(matches!(
+            Header::read(&mut &buffer[..]),
+            Err(Error::InvalidStageCount(0))
+        ));
+
+        let mut buffer = MAGIC.to_vec();
+        buffer.extend([VERSION, 1, 1, 3, 40, 0, 64]);
+        assert!(matches!(
+            Header::read(&mut &buffer[..]),
+            Err(Error::UnsupportedWordSize(40))
+        ));
+    }
+
+    #[test]
+    fn should_reject_malformed_parameters() {
+        for (id, params) in [
+            (1, &[8, 0][..]),
+            (1, &[8, 0, 64, 0]),
+            (1, &[8, 2, 64]),
+            (1, &[8, 0, 0]),
+            (1, &[8, 1, 65]),
+            (2, &[0, 0, 0, 0, 0, 0, 0, 0, 16]),
+            (2, &[2, 0, 0, 0, 0, 0, 0, 0, 0, 16]),
+            (5, &[6, 0, 0, 0, 0, 0, 0, 0, 16]),
+        ] {
+            let mut buffer = MAGIC.to_vec();
+            buffer.extend([VERSION, 1, id, params.len() as u8]);
+            buffer.extend(params);
+            buffer.extend([0; 4]);
+
+            assert!(matches!(
+                Header::read(&mut &buffer[..]),
+                Err(Error::InvalidParameters(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn should_reject_invalid_block_sizes() {
+        for block_size in [0, 1 << 30] {
+            for codec in [
+                Codec::HuffmanStream {
+                    options: huffman::Options::new(8),
+                    block_size,
+                },
+                Codec::Bwt { block_size },
+            ] {
+                let mut buffer = Vec::new();
+                Header::new(codec).write(&mut buffer).unwrap();
+
+                assert!(matches!(
+                    Header::read(&mut &buffer[..]),
+                    Err(Error::Huffman(huffman::Error::InvalidBlockSize(_))
+                        | Error::Bwt(bwt::Error::InvalidBlockSize(_)))
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Delta transform, every byte is replaced by its difference from the previous
+//! one. Slowly changing data, such as samples or tables of counters, turns into
+//! small repeating values that the other codecs handle better.
+
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use crate::error::Result;
+
+pub fn encode(input: impl Read, output: impl Write) -> Result<()> {
+    transform(input, output, |previous, byte| {
+        let delta = byte.wrapping_sub(*previous);
+        *previous = byte;
+        delta
+    })
+}
+
+/// Reverts [`encode`].
+pub fn decode(input: impl Read, output: impl Write) -> Result<()> {
+    transform(input, output, |previous, byte| {
+        *previous = previous.wrapping_add(byte);
+        *previous
+    })
+}
+
+fn transform(
+    input: impl Read,
+    output: impl Write,
+    mut map: impl FnMut(&mut u8, u8) -> u8,
+) -> Result<()> {
+    let mut reader = BufReader::new(input);
+    let mut writer = BufWriter::new(output);
+    let mut buffer = [0u8; 1 << 16];
+    let mut previous = 0;
+
+    loop {
+        let length = reader.read(&mut buffer)?;
+
+        if length == 0 {
+            break;
+        }
+
+        for byte in &mut buffer[..length] {
+            *byte = map(&mut previous, *byte);
+        }
+
+        writer.write_all(&buffer[..length])?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, encode};
+
+    #[test]
+    fn should_store_differences() {
+        let data = [10, 12, 14, 13, 13, 0, 255];
+        let mut encoded = Vec::new();
+        encode(&data[..], &mut encoded).unwrap();
+
+        assert_eq!(encoded, [10, 2, 2, 255, 0, 243, 255]);
+
+        let mut decoded = Vec::new();
+        decode(&encoded[..], &mut decoded).unwrap();
+        assert_eq!(decoded, data);
+    }
+}
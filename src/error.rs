@@ -0,0 +1,72 @@
+use std::io;
+
+use thiserror::Error;
+
+use crate::pipeline::Pipeline;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Not an infteor compressed file")]
+    NotInfteorFile,
+    #[error("Not an infteor compressed file: header is truncated")]
+    TruncatedHeader,
+    #[error("Not an infteor archive")]
+    NotArchive,
+    #[error("Corrupt archive index: {0}")]
+    CorruptIndex(&'static str),
+    #[error("Archive can not hold more than {} entries", u32::MAX)]
+    TooManyEntries,
+    /// path is absolute, leaves its directory or is not valid UTF-8
+    #[error("Entry path {0:?} is not a relative path within the archive")]
+    InvalidEntryPath(String),
+    #[error("Unsupported format version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Unknown codec id {0}")]
+    UnknownCodec(u8),
+    /// parameters of a codec are malformed or out of range
+    #[error("Invalid {0} parameters")]
+    InvalidParameters(&'static str),
+    #[error("Unsupported word size {0}")]
+    UnsupportedWordSize(u8),
+    #[error(
+        "Pipeline must have between 1 and {max} stages, got {0}",
+        max = Pipeline::MAX_STAGES
+    )]
+    InvalidStageCount(usize),
+    #[error("Compressed file is truncated: trailer is missing")]
+    TruncatedTrailer,
+    #[error("Compressed block {0} is truncated")]
+    TruncatedBlock(u64),
+    #[error("File has no block index, it was not compressed in blocks")]
+    MissingBlockIndex,
+    #[error("Corrupt block index")]
+    CorruptBlockIndex,
+    #[error("Block {index} does not exist, there are {count} blocks")]
+    BlockOutOfRange { index: u64, count: u64 },
+    #[error("Decompressed {actual} bytes, while the file records {expected}")]
+    SizeMismatch { expected: u64, actual: u64 },
+    #[error("Decompressed data has CRC-32 {actual:08x}, while the file records {expected:08x}")]
+    ChecksumMismatch { expected: u32, actual: u32 },
+    #[error(
+        "Decompressed block {index} has CRC-32 {actual:08x}, while the file records {expected:08x}"
+    )]
+    BlockChecksumMismatch {
+        index: u64,
+        expected: u32,
+        actual: u32,
+    },
+    #[error(transparent)]
+    Huffman(#[from] huffman::Error),
+    #[error(transparent)]
+    Lz78(#[from] lz78::Error),
+    #[error(transparent)]
+    Lz77(#[from] lz77::Error),
+    #[error(transparent)]
+    Entropy(#[from] entropy::Error),
+    #[error(transparent)]
+    Bwt(#[from] bwt::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,697 @@
+//! Compression with codecs of the workspace crates, wrapped in a container
+//! recording how the data was compressed.
+//!
+//! Data already in memory is best passed to [`compress_to_vec`] and
+//! [`decompress_to_vec`]: the slice is read in place through [`Cursor`],
+//! without copying it upfront. The same holds for [`Encoder`] and [`Decoder`]
+//! given a `Cursor<&[u8]>` or `&[u8]` respectively.
+//!
+//! Size and CRC-32 of the original data are recorded in a trailer following
+//! the compressed data, so [`compress`] reads its input only once, and they
+//! are checked once it is decompressed. [`decompress_with`] can skip the
+//! checksum, [`verify`] runs the same checks without keeping the output.
+//!
+//! [`compress_blocks`] splits input into blocks compressed independently on
+//! several threads, which are decompressed in parallel as well. Any one of
+//! them is decompressed on its own by [`decompress_block`].
+//!
+//! Many files and directories are packed into one file by [`ArchiveWriter`],
+//! with every file compressed as a container of its own.
+
+pub mod archive;
+pub mod block;
+pub mod checksum;
+pub mod container;
+pub mod delta;
+pub mod error;
+pub mod pipeline;
+
+use std::{
+    io::{self, Cursor, Read, Seek, Write},
+    num::NonZeroUsize,
+};
+
+pub use archive::{ArchiveReader, ArchiveWriter};
+use checksum::Crc32;
+use container::PayloadReader;
+pub use container::{Codec, Header, Trailer};
+pub use error::{Error, Result};
+pub use pipeline::{Decoder, Encoder, Pipeline};
+
+/// Largest output buffer allocated upfront from the size stored in a trailer,
+/// which may be damaged. Larger outputs grow as they are written.
+const MAX_PREALLOCATION: u64 = 1 << 26;
+
+/// Compresses `input` with `pipeline`, reading it once. Output is wrapped in
+/// a container header and trailer, the trailer is returned.
+///
+/// Pipelines reading their input twice, see [`Pipeline::reads_input_twice`],
+/// keep it in memory, [`compress_seekable`] reads it again instead.
+pub fn compress(
+    pipeline: impl Into<Pipeline>,
+    input: impl Read,
+    mut output: impl Write,
+) -> Result<Trailer> {
+    let header = Header::new(pipeline);
+    header.write(&mut output)?;
+
+    let mut input = SummaryReader::new(input);
+    header.pipeline.encode_stream(&mut input, &mut output)?;
+
+    finish(input.trailer(), output)
+}
+
+/// Same as [`compress`], input of pipelines reading it twice is read from the
+/// start once more rather than kept in memory.
+pub fn compress_seekable(
+    pipeline: impl Into<Pipeline>,
+    mut input: impl Read + Seek,
+    mut output: impl Write,
+) -> Result<Trailer> {
+    let header = Header::new(pipeline);
+
+    if !header.pipeline.reads_input_twice() {
+        return compress(header.pipeline, input, output);
+    }
+
+    let trailer = summarize(&mut input)?;
+    header.write(&mut output)?;
+    header.pipeline.encode(&mut input, &mut output)?;
+
+    finish(trailer, output)
+}
+
+/// Same as [`compress`], but input is split into blocks of `block_size`
+/// bytes, compressed independently of each other, `threads` at a time.
+pub fn compress_blocks(
+    pipeline: impl Into<Pipeline>,
+    block_size: u32,
+    threads: NonZeroUsize,
+    input: impl Read,
+    mut output: impl Write,
+) -> Result<Trailer> {
+    if block_size == 0 {
+        return Err(Error::InvalidParameters("block"));
+    }
+
+    let header = Header {
+        block_size: Some(block_size),
+        ..Header::new(pipeline)
+    };
+    header.write(&mut output)?;
+
+    let mut input = SummaryReader::new(input);
+    block::encode(
+        &header.pipeline,
+        block_size,
+        threads,
+        &mut input,
+        &mut output,
+    )?;
+
+    finish(input.trailer(), output)
+}
+
+/// Size and checksum of the whole input, which is rewound afterwards, as
+/// codecs read it again from the start.
+fn summarize(input: &mut (impl Read + Seek)) -> Result<Trailer> {
+    input.rewind()?;
+    let mut crc = Crc32::new();
+    let size = io::copy(input, &mut crc)?;
+    input.rewind()?;
+
+    Ok(Trailer {
+        size,
+        checksum: crc.finish(),
+    })
+}
+
+/// Writes `trailer` after the payload and returns it.
+fn finish(trailer: Trailer, mut output: impl Write) -> Result<Trailer> {
+    trailer.write(&mut output)?;
+
+    Ok(trailer)
+}
+
+/// Decompresses `input` with the pipeline recorded in its container header.
+pub fn decompress(input: impl Read, output: impl Write) -> Result<Pipeline> {
+    decompress_with(true, input, output)
+}
+
+/// Same as [`decompress`], the checksum is computed only if `verify_checksum`
+/// is set. Size of the output is checked either way.
+pub fn decompress_with(
+    verify_checksum: bool,
+    input: impl Read,
+    output: impl Write,
+) -> Result<Pipeline> {
+    decompress_parallel(verify_checksum, block::default_threads(), input, output)
+}
+
+/// Same as [`decompress_with`], decoding at most `threads` blocks at a time
+/// if the data was compressed by [`compress_blocks`].
+pub fn decompress_parallel(
+    verify_checksum: bool,
+    threads: NonZeroUsize,
+    mut input: impl Read,
+    output: impl Write,
+) -> Result<Pipeline> {
+    let header = Header::read(&mut input)?;
+    decode(&header, verify_checksum, threads, input, output)?;
+
+    Ok(header.pipeline)
+}
+
+/// Decompresses block number `index` of a file written by
+/// [`compress_blocks`] without decoding the blocks before it, which the block
+/// index at the end of the file allows. `input` holds just that file, from
+/// its current position on. Output has `block_size` bytes, less for the last
+/// block.
+pub fn decompress_block(
+    mut input: impl Read + Seek,
+    index: u64,
+    mut output: impl Write,
+) -> Result<()> {
+    block::decode_block(&mut input, index, &mut output)
+}
+
+/// Decodes `input` without keeping the output, checking its size and
+/// checksum against the recorded ones. Header is returned along with the
+/// trailer holding them.
+pub fn verify(mut input: impl Read) -> Result<(Header, Trailer)> {
+    let header = Header::read(&mut input)?;
+    let trailer = decode(&header, true, block::default_threads(), input, io::sink())?;
+
+    Ok((header, trailer))
+}
+
+/// Compresses `input` with `pipeline` into a new buffer, along with a header.
+pub fn compress_to_vec(pipeline: impl Into<Pipeline>, input: &[u8]) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    compress_seekable(pipeline, Cursor::new(input), &mut output)?;
+
+    Ok(output)
+}
+
+/// Decompresses output of [`compress_to_vec`] or [`compress`] into a buffer
+/// preallocated to the size recorded in its trailer.
+pub fn decompress_to_vec(mut input: &[u8]) -> Result<Vec<u8>> {
+    let header = Header::read(&mut input)?;
+    let capacity = match input.len().checked_sub(Trailer::LEN) {
+        Some(start) => Trailer::read(&mut &input[start..])?.size,
+        None => 0,
+    };
+
+    let mut output = Vec::with_capacity(capacity.min(MAX_PREALLOCATION) as usize);
+    decode(&header, true, block::default_threads(), input, &mut output)?;
+
+    Ok(output)
+}
+
+/// Decodes payload following `header`, checking its size and, if asked to,
+/// checksum against those in the trailer, which is returned.
+fn decode(
+    header: &Header,
+    verify_checksum: bool,
+    threads: NonZeroUsize,
+    input: impl Read,
+    output: impl Write,
+) -> Result<Trailer> {
+    let mut output = SummaryWriter {
+        inner: output,
+        count: 0,
+        crc: verify_checksum.then(Crc32::new),
+    };
+
+    let mut input = PayloadReader::new(input);
+    decode_payload(header, verify_checksum, threads, &mut input, &mut output)?;
+    let trailer = input.finish()?;
+
+    if output.count != trailer.size {
+        return Err(Error::SizeMismatch {
+            expected: trailer.size,
+            actual: output.count,
+        });
+    }
+
+    if let Some(crc) = &output.crc {
+        if crc.finish() != trailer.checksum {
+            return Err(Error::ChecksumMismatch {
+                expected: trailer.checksum,
+                actual: crc.finish(),
+            });
+        }
+    }
+
+    Ok(trailer)
+}
+
+fn decode_payload(
+    header: &Header,
+    verify_checksums: bool,
+    threads: NonZeroUsize,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> Result<()> {
+    match header.block_size {
+        Some(block_size) => block::decode(
+            &header.pipeline,
+            block_size,
+            verify_checksums,
+            threads,
+            input,
+            output,
+        ),
+        None => header.pipeline.decode(input, output),
+    }
+}
+
+/// Counts and checksums the data read through it.
+struct SummaryReader<R> {
+    inner: R,
+    count: u64,
+    crc: Crc32,
+}
+
+impl<R: Read> SummaryReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            count: 0,
+            crc: Crc32::new(),
+        }
+    }
+
+    fn trailer(&self) -> Trailer {
+        Trailer {
+            size: self.count,
+            checksum: self.crc.finish(),
+        }
+    }
+}
+
+impl<R: Read> Read for SummaryReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.count += len as u64;
+        self.crc.update(&buf[..len]);
+
+        Ok(len)
+    }
+}
+
+/// Counts and optionally checksums the data written through it.
+struct SummaryWriter<W> {
+    inner: W,
+    count: u64,
+    crc: Option<Crc32>,
+}
+
+impl<W: Write> Write for SummaryWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+
+        if let Some(crc) = &mut self.crc {
+            crc.update(&buf[..written]);
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{io::Cursor, num::NonZeroUsize};
+
+    use lz78::dictionary::PruningStrategy;
+
+    use super::{
+        block::BLOCK_HEADER_LEN, checksum::crc32, compress, compress_blocks, compress_to_vec,
+        decompress, decompress_block, decompress_parallel, decompress_to_vec, decompress_with,
+        verify, Codec, Error, Header, Pipeline, Trailer,
+    };
+
+    #[test]
+    fn roundtrip_detects_codec() {
+        let data = b"abracadabra abracadabra abracadabra";
+
+        for codec in [
+            Codec::Huffman(huffman::Options::new(9)),
+            Codec::Huffman(huffman::Options {
+                tree_format: huffman::TreeFormat::Canonical,
+                max_code_length: 12,
+                ..huffman::Options::new(11)
+            }),
+            Codec::Lz78 {
+                strategy: PruningStrategy::Drop(16),
+                format: lz78::TokenFormat::Gamma,
+            },
+            Codec::Lz78 {
+                strategy: PruningStrategy::Freeze(16),
+                format: lz78::TokenFormat::Compact,
+            },
+            Codec::HuffmanStream {
+                options: huffman::Options::new(4),
+                block_size: 8,
+            },
+            Codec::AdaptiveHuffman { word_size: 6 },
+            Codec::Lzw {
+                strategy: PruningStrategy::Freeze(8),
+            },
+            Codec::Lz77(lz77::Options::new(16, 3)),
+            Codec::Range { word_size: 5 },
+            Codec::AdaptiveRange { word_size: 8 },
+            Codec::Tans { word_size: 8 },
+            Codec::AdaptiveRans { word_size: 3 },
+            Codec::Bwt { block_size: 64 },
+            Codec::Delta,
+        ] {
+            let mut compressed = Vec::new();
+            compress(codec, Cursor::new(&data[..]), &mut compressed).unwrap();
+
+            let mut output = Vec::new();
+            let detected = decompress(&compressed[..], &mut output).unwrap();
+
+            assert_eq!(detected, Pipeline::new(codec));
+            assert_eq!(output, data);
+        }
+    }
+
+    #[test]
+    fn roundtrip_detects_pipeline() {
+        let data = b"abracadabra abracadabra abracadabra";
+        let pipeline = Pipeline::new(Codec::Lz78 {
+            strategy: PruningStrategy::Never,
+            format: lz78::TokenFormat::Gamma,
+        })
+        .then(Codec::Huffman(huffman::Options::new(8)));
+
+        let mut compressed = Vec::new();
+        compress(pipeline.clone(), Cursor::new(&data[..]), &mut compressed).unwrap();
+
+        let mut output = Vec::new();
+        let detected = decompress(&compressed[..], &mut output).unwrap();
+
+        assert_eq!(detected, pipeline);
+        assert_eq!(output, data);
+    }
+
+    #[test]
+    fn roundtrip_in_memory() {
+        let data = b"abracadabra abracadabra abracadabra";
+
+        for codec in [
+            Codec::Huffman(huffman::Options::new(8)),
+            Codec::Lz77(lz77::Options::new(16, 3)),
+            Codec::Tans { word_size: 8 },
+        ] {
+            let compressed = compress_to_vec(codec, data).unwrap();
+
+            assert_eq!(decompress_to_vec(&compressed).unwrap(), data);
+            assert_eq!(
+                decompress_to_vec(&compressed).unwrap().capacity(),
+                data.len()
+            );
+        }
+    }
+
+    #[test]
+    fn should_preallocate_from_trailer() {
+        let data = b"abracadabra ".repeat(1000);
+        let compressed = compress_to_vec(Codec::Huffman(huffman::Options::new(8)), &data).unwrap();
+
+        assert_eq!(
+            decompress_to_vec(&compressed).unwrap().capacity(),
+            data.len()
+        );
+    }
+
+    #[test]
+    fn should_reject_wrong_size() {
+        let mut compressed = compress_to_vec(Codec::Delta, b"abracadabra").unwrap();
+        // last byte of the size, followed by the checksum
+        let last = compressed.len() - 5;
+        compressed[last] += 1;
+
+        assert!(matches!(
+            decompress_to_vec(&compressed),
+            Err(Error::SizeMismatch {
+                expected: 12,
+                actual: 11
+            })
+        ));
+        assert!(decompress(&compressed[..], Vec::new()).is_err());
+    }
+
+    #[test]
+    fn should_verify_without_output() {
+        let data = b"abracadabra abracadabra";
+        let pipeline = Pipeline::new(Codec::Lz78 {
+            strategy: PruningStrategy::Never,
+            format: lz78::TokenFormat::Gamma,
+        })
+        .then(Codec::Huffman(huffman::Options::new(8)));
+        let mut compressed = compress_to_vec(pipeline.clone(), data).unwrap();
+
+        let (header, trailer) = verify(&compressed[..]).unwrap();
+        assert_eq!(header.pipeline, pipeline);
+        assert_eq!(trailer.size, data.len() as u64);
+        assert_eq!(trailer.checksum, crc32(data));
+
+        compressed.truncate(compressed.len() - 1);
+        assert!(verify(&compressed[..]).is_err());
+    }
+
+    #[test]
+    fn should_detect_corrupted_payload() {
+        let data = b"abracadabra";
+        let mut compressed = compress_to_vec(Codec::Delta, data).unwrap();
+        let last = compressed.len() - Trailer::LEN - 1;
+        compressed[last] ^= 0x10;
+
+        assert!(matches!(
+            decompress_to_vec(&compressed),
+            Err(Error::ChecksumMismatch { expected, .. }) if expected == crc32(data)
+        ));
+        assert!(matches!(
+            decompress(&compressed[..], Vec::new()),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+
+        let mut output = Vec::new();
+        decompress_with(false, &compressed[..], &mut output).unwrap();
+        assert_eq!(output[..10], data[..10]);
+        assert_ne!(output[10], data[10]);
+    }
+
+    #[test]
+    fn should_roundtrip_blocks() {
+        let data = b"abracadabra abracadabra".repeat(40);
+        let pipeline = Pipeline::new(Codec::Lz78 {
+            strategy: PruningStrategy::Never,
+            format: lz78::TokenFormat::Gamma,
+        })
+        .then(Codec::Huffman(huffman::Options::new(8)));
+
+        for (block_size, threads) in [(100, 3), (92, 4), (1, 16), (4096, 2)] {
+            let threads = NonZeroUsize::new(threads).unwrap();
+
+            for data in [&data[..], &data[..100], &[]] {
+                let mut compressed = Vec::new();
+                let trailer = compress_blocks(
+                    pipeline.clone(),
+                    block_size,
+                    threads,
+                    Cursor::new(data),
+                    &mut compressed,
+                )
+                .unwrap();
+                assert_eq!(trailer.size, data.len() as u64);
+                assert_eq!(
+                    Header::read(&mut &compressed[..]).unwrap().block_size,
+                    Some(block_size)
+                );
+
+                assert_eq!(decompress_to_vec(&compressed).unwrap(), data);
+
+                let mut output = Vec::new();
+                decompress_parallel(true, NonZeroUsize::MIN, &compressed[..], &mut output).unwrap();
+                assert_eq!(output, data);
+            }
+        }
+
+        assert!(matches!(
+            compress_blocks(
+                Codec::Delta,
+                0,
+                NonZeroUsize::MIN,
+                Cursor::new(b""),
+                Vec::new()
+            ),
+            Err(Error::InvalidParameters(_))
+        ));
+    }
+
+    /// Start of the block index of `compressed` and the offsets of blocks it
+    /// lists, from the start of the file.
+    fn block_index(compressed: &[u8]) -> (usize, Vec<usize>) {
+        let mut payload = compressed;
+        Header::read(&mut payload).unwrap();
+        let payload = compressed.len() - payload.len();
+
+        let count_offset = compressed.len() - Trailer::LEN - 8;
+        let entry = |offset: usize| {
+            u64::from_be_bytes(compressed[offset..offset + 8].try_into().unwrap()) as usize
+        };
+        let index = count_offset - 8 * entry(count_offset);
+        let offsets = (index..count_offset)
+            .step_by(8)
+            .map(|offset| payload + entry(offset))
+            .collect();
+
+        (index, offsets)
+    }
+
+    #[test]
+    fn should_detect_damaged_blocks() {
+        let data = b"abracadabra".repeat(10);
+        let threads = NonZeroUsize::new(4).unwrap();
+        let mut compressed = Vec::new();
+        compress_blocks(
+            Codec::Delta,
+            30,
+            threads,
+            Cursor::new(&data),
+            &mut compressed,
+        )
+        .unwrap();
+
+        // end of the index is cut, as the trailer is taken from what is left
+        assert!(matches!(
+            decompress_to_vec(&compressed[..compressed.len() - 1]),
+            Err(Error::CorruptBlockIndex)
+        ));
+
+        // byte of the second block, past its length and checksum
+        let (index, offsets) = block_index(&compressed);
+        let mut damaged = compressed.clone();
+        damaged[offsets[1] + BLOCK_HEADER_LEN as usize + 5] ^= 1;
+        assert!(matches!(
+            decompress_to_vec(&damaged),
+            Err(Error::BlockChecksumMismatch { index: 1, .. })
+        ));
+
+        // last block, which holds 20 bytes, loses its last one, right before
+        // the end marker, and the low byte of its length goes down with it
+        compressed[offsets[3] + 7] -= 1;
+        compressed.remove(index - 8 - 1);
+        assert!(matches!(
+            decompress_to_vec(&compressed),
+            Err(Error::BlockChecksumMismatch { index: 3, .. })
+        ));
+        assert!(matches!(
+            decompress_with(false, &compressed[..], Vec::new()),
+            Err(Error::SizeMismatch {
+                expected: 110,
+                actual: 109
+            })
+        ));
+    }
+
+    #[test]
+    fn should_decompress_single_blocks() {
+        let data = b"abracadabra".repeat(10);
+        let threads = NonZeroUsize::new(4).unwrap();
+        let mut compressed = Vec::new();
+        compress_blocks(
+            Codec::AdaptiveHuffman { word_size: 8 },
+            30,
+            threads,
+            Cursor::new(&data),
+            &mut compressed,
+        )
+        .unwrap();
+
+        for (index, expected) in data.chunks(30).enumerate() {
+            let mut block = Vec::new();
+            decompress_block(Cursor::new(&compressed), index as u64, &mut block).unwrap();
+            assert_eq!(block, expected);
+        }
+
+        assert!(matches!(
+            decompress_block(Cursor::new(&compressed), 4, Vec::new()),
+            Err(Error::BlockOutOfRange { index: 4, count: 4 })
+        ));
+        assert!(matches!(
+            decompress_block(
+                Cursor::new(
+                    compress_to_vec(Codec::AdaptiveHuffman { word_size: 8 }, &data).unwrap()
+                ),
+                0,
+                Vec::new()
+            ),
+            Err(Error::MissingBlockIndex)
+        ));
+
+        // offset of the second block points past the data
+        let (index, _) = block_index(&compressed);
+        compressed[index + 8] ^= 1;
+        assert!(matches!(
+            decompress_block(Cursor::new(&compressed), 1, Vec::new()),
+            Err(Error::TruncatedBlock(1))
+        ));
+        assert!(matches!(
+            decompress_to_vec(&compressed),
+            Err(Error::CorruptBlockIndex)
+        ));
+    }
+
+    #[test]
+    fn should_compress_unseekable_input() {
+        let data = b"abracadabra abracadabra abracadabra";
+
+        // Huffman reads its input twice, LZ78 once
+        for codec in [
+            Codec::Huffman(huffman::Options::new(8)),
+            Codec::Lz78 {
+                strategy: PruningStrategy::Never,
+                format: lz78::TokenFormat::Gamma,
+            },
+        ] {
+            let mut compressed = Vec::new();
+            let trailer = compress(codec, &data[..], &mut compressed).unwrap();
+
+            assert_eq!(trailer.size, data.len() as u64);
+            assert_eq!(trailer.checksum, crc32(data));
+            assert_eq!(compressed, compress_to_vec(codec, data).unwrap());
+            assert_eq!(decompress_to_vec(&compressed).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn should_reject_raw_codec_output() {
+        let mut compressed = Vec::new();
+        lz78::encode(&b"abracadabra"[..], &mut compressed, PruningStrategy::Never).unwrap();
+
+        assert!(matches!(
+            decompress(&compressed[..], Vec::new()),
+            Err(Error::NotInfteorFile)
+        ));
+    }
+
+    #[test]
+    fn should_report_codec_errors() {
+        let compressed =
+            compress_to_vec(Codec::Lz77(lz77::Options::new(16, 3)), b"abracadabra").unwrap();
+
+        assert!(matches!(
+            decompress_to_vec(&compressed[..compressed.len() - 2]),
+            Err(Error::Lz77(lz77::Error::UnexpectedEof))
+        ));
+    }
+}
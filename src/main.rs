@@ -1,8 +1,19 @@
-#![feature(path_add_extension)]
-
-use anyhow::Result;
-use clap::{Parser, Subcommand};
-use std::{fs::File, path::PathBuf};
+use anyhow::{bail, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use huffman::TreeFormat;
+use infteor::{
+    archive::{Entry, EntryKind},
+    ArchiveReader, ArchiveWriter, Codec, Header, Pipeline, Trailer,
+};
+use lz78::dictionary::PruningStrategy;
+use std::{
+    ffi::{OsStr, OsString},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, IsTerminal, Read, Write},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    process,
+};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -11,19 +22,268 @@ struct Config {
     command: Commands,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Algorithm {
+    Huffman,
+    AdaptiveHuffman,
+    Lz78,
+    Lzw,
+    Lz77,
+    Range,
+    AdaptiveRange,
+    Tans,
+    AdaptiveRans,
+    Bwt,
+    Delta,
+}
+
+/// What LZ78 and LZW do once their dictionary reaches `--max-entries` phrases
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Prune {
+    /// Drop the whole dictionary and start over
+    Drop,
+    /// Keep the dictionary as is
+    Freeze,
+    /// Replace the least recently used phrase, LZ78 only
+    Lru,
+    /// Replace the least frequently used phrase, LZ78 only
+    Lfu,
+    /// Freeze, and drop the dictionary once compression ratio degrades, LZ78 only
+    Reset,
+}
+
+impl Algorithm {
+    /// Algorithm compressing with `codec`.
+    fn of(codec: &Codec) -> Self {
+        match codec {
+            Codec::Huffman(_) | Codec::HuffmanStream { .. } => Algorithm::Huffman,
+            Codec::AdaptiveHuffman { .. } => Algorithm::AdaptiveHuffman,
+            Codec::Lz78 { .. } => Algorithm::Lz78,
+            Codec::Lzw { .. } => Algorithm::Lzw,
+            Codec::Lz77(_) => Algorithm::Lz77,
+            Codec::Range { .. } => Algorithm::Range,
+            Codec::AdaptiveRange { .. } => Algorithm::AdaptiveRange,
+            Codec::Tans { .. } => Algorithm::Tans,
+            Codec::AdaptiveRans { .. } => Algorithm::AdaptiveRans,
+            Codec::Bwt { .. } => Algorithm::Bwt,
+            Codec::Delta => Algorithm::Delta,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Algorithm::Huffman => "huff",
+            Algorithm::AdaptiveHuffman => "ahuff",
+            Algorithm::Lz78 => "lz78",
+            Algorithm::Lzw => "lzw",
+            Algorithm::Lz77 => "lz77",
+            Algorithm::Range => "rc",
+            Algorithm::AdaptiveRange => "arc",
+            Algorithm::Tans => "tans",
+            Algorithm::AdaptiveRans => "arans",
+            Algorithm::Bwt => "bwt",
+            Algorithm::Delta => "delta",
+        }
+    }
+}
+
+// parameters shared by all compression algorithms, each uses only some of them
+#[derive(Debug, Args)]
+struct CodecArgs {
+    /// Size of a single Huffman, range or ANS coder word in bits
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u8).range(2..=17))]
+    word_size: u8,
+    /// Compress Huffman input in blocks of this many bytes, without seeking
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=huffman::MAX_BLOCK_SIZE as i64))]
+    block_size: Option<u32>,
+    /// Size of BWT blocks in bytes
+    #[arg(
+        long,
+        default_value_t = bwt::DEFAULT_BLOCK_SIZE,
+        value_parser = clap::value_parser!(u32).range(1..=bwt::MAX_BLOCK_SIZE as i64)
+    )]
+    bwt_block_size: u32,
+    /// Store canonical Huffman code lengths instead of the full tree
+    #[arg(long)]
+    canonical: bool,
+    /// Longest allowed Huffman code in bits
+    #[arg(long, default_value_t = 64, value_parser = clap::value_parser!(u8).range(2..=64))]
+    max_code_length: u8,
+    /// Largest number of LZ78/LZW dictionary phrases, unlimited by default
+    #[arg(long)]
+    max_entries: Option<u64>,
+    /// What to do once the dictionary reaches `--max-entries` phrases
+    #[arg(long, value_enum, default_value_t = Prune::Drop, requires = "max_entries")]
+    prune: Prune,
+    /// Write LZ78 indices in truncated binary and code literals with adaptive Huffman
+    #[arg(long)]
+    compact_tokens: bool,
+    /// LZ77 window size in bytes
+    #[arg(long, default_value_t = lz77::Options::default().window_size)]
+    window_size: u32,
+    /// Shortest LZ77 match in bytes, shorter ones are stored as literals
+    #[arg(long, default_value_t = lz77::Options::default().min_match)]
+    min_match: u8,
+}
+
+impl CodecArgs {
+    fn strategy(&self) -> PruningStrategy {
+        let Some(limit) = self.max_entries else {
+            return PruningStrategy::Never;
+        };
+
+        match self.prune {
+            Prune::Drop => PruningStrategy::Drop(limit),
+            Prune::Freeze => PruningStrategy::Freeze(limit),
+            Prune::Lru => PruningStrategy::Lru(limit),
+            Prune::Lfu => PruningStrategy::Lfu(limit),
+            Prune::Reset => PruningStrategy::Reset(limit),
+        }
+    }
+
+    fn codec(&self, algorithm: Algorithm) -> Codec {
+        let word_size = self.word_size;
+        let options = huffman::Options {
+            word_size,
+            tree_format: if self.canonical {
+                TreeFormat::Canonical
+            } else {
+                TreeFormat::Full
+            },
+            max_code_length: self.max_code_length,
+        };
+
+        match algorithm {
+            Algorithm::Huffman => match self.block_size {
+                Some(block_size) => Codec::HuffmanStream {
+                    options,
+                    block_size,
+                },
+                None => Codec::Huffman(options),
+            },
+            Algorithm::AdaptiveHuffman => Codec::AdaptiveHuffman { word_size },
+            Algorithm::Lz78 => Codec::Lz78 {
+                strategy: self.strategy(),
+                format: if self.compact_tokens {
+                    lz78::TokenFormat::Compact
+                } else {
+                    lz78::TokenFormat::Gamma
+                },
+            },
+            Algorithm::Lzw => Codec::Lzw {
+                strategy: self.strategy(),
+            },
+            Algorithm::Lz77 => Codec::Lz77(lz77::Options::new(self.window_size, self.min_match)),
+            Algorithm::Range => Codec::Range { word_size },
+            Algorithm::AdaptiveRange => Codec::AdaptiveRange { word_size },
+            Algorithm::Tans => Codec::Tans { word_size },
+            Algorithm::AdaptiveRans => Codec::AdaptiveRans { word_size },
+            Algorithm::Bwt => Codec::Bwt {
+                block_size: self.bwt_block_size,
+            },
+            Algorithm::Delta => Codec::Delta,
+        }
+    }
+
+    /// Pipeline of `algorithm` followed by every one of `then`
+    fn pipeline(&self, algorithm: Algorithm, then: &[Algorithm]) -> Pipeline {
+        then.iter().fold(
+            Pipeline::new(self.codec(algorithm)),
+            |pipeline, &algorithm| pipeline.then(self.codec(algorithm)),
+        )
+    }
+}
+
 #[derive(Debug, Subcommand)]
 enum Commands {
     Compress {
+        /// File to compress, `-` for stdin
         input: PathBuf,
+        /// Output file, `-` for stdout
         #[arg(long, short)]
         output: Option<PathBuf>,
-        #[arg(long)]
-        dictionary_size: i64,
+        /// Write to stdout
+        #[arg(long = "stdout", short = 'c', conflicts_with = "output")]
+        to_stdout: bool,
+        #[arg(long, short, value_enum, default_value_t = Algorithm::Lz78)]
+        algorithm: Algorithm,
+        /// Further algorithms, each applied to the output of the previous one
+        #[arg(long, value_enum)]
+        then: Vec<Algorithm>,
+        /// Split input into independent blocks, compressing this many of
+        /// them at once
+        #[arg(long, short = 'T')]
+        threads: Option<NonZeroUsize>,
+        /// Size of independent blocks in bytes (1048576 by default)
+        #[arg(long, requires = "threads", value_parser = clap::value_parser!(u32).range(1..))]
+        parallel_block_size: Option<u32>,
+        #[command(flatten)]
+        codec: CodecArgs,
     },
     Decompress {
+        /// File to decompress, `-` for stdin
         input: PathBuf,
+        /// Output file, `-` for stdout
         #[arg(long, short)]
         output: Option<PathBuf>,
+        /// Write to stdout
+        #[arg(long = "stdout", short = 'c', conflicts_with = "output")]
+        to_stdout: bool,
+        /// Skip checking the checksum of decompressed data
+        #[arg(long)]
+        no_verify: bool,
+        /// Decompress this many independent blocks at once, one for every
+        /// core by default
+        #[arg(long, short = 'T')]
+        threads: Option<NonZeroUsize>,
+    },
+    /// Decode compressed files without writing them out, checking their
+    /// headers, sizes and checksums
+    #[command(alias = "verify")]
+    Test {
+        /// Files to check, `-` for stdin
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+    },
+    /// Pack files and directories into a single archive, each file
+    /// compressed on its own
+    Archive {
+        #[command(subcommand)]
+        command: ArchiveCommands,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ArchiveCommands {
+    /// Create an archive, adding directories with everything in them
+    ///
+    /// Every file is compressed with the same pipeline, there is no choice of
+    /// codec per file nor fallback to storing files that do not shrink.
+    Create {
+        /// Archive file to create
+        archive: PathBuf,
+        /// Files and directories to add
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        #[arg(long, short, value_enum, default_value_t = Algorithm::Lz78)]
+        algorithm: Algorithm,
+        /// Further algorithms, each applied to the output of the previous one
+        #[arg(long, value_enum)]
+        then: Vec<Algorithm>,
+        #[command(flatten)]
+        codec: CodecArgs,
+    },
+    /// List entries of an archive
+    List { archive: PathBuf },
+    /// Extract every entry of an archive, recreating its directories
+    Extract {
+        archive: PathBuf,
+        /// Directory to extract into
+        #[arg(long, short = 'C', default_value = ".")]
+        directory: PathBuf,
+        /// Skip checking the checksums of extracted files
+        #[arg(long)]
+        no_verify: bool,
     },
 }
 
@@ -32,26 +292,362 @@ fn run() -> Result<()> {
 
     match args.command {
         Commands::Compress {
-            dictionary_size,
+            algorithm,
+            then,
+            codec,
             input,
             output,
+            to_stdout,
+            threads,
+            parallel_block_size,
+        } => {
+            let pipeline = codec.pipeline(algorithm, &then);
+            let default_output = then.iter().fold(
+                input.with_added_extension(algorithm.extension()),
+                |path, algorithm| path.with_added_extension(algorithm.extension()),
+            );
+
+            let output = output_path(&input, output, to_stdout, || Ok(default_output))?;
+
+            if output.is_none() && io::stdout().is_terminal() {
+                bail!("Compressed data is not written to a terminal, redirect it or use --output");
+            }
+
+            let mut output = create_output(output.as_deref())?;
+
+            // blocks are read into memory one at a time, whatever the pipeline
+            match threads {
+                Some(threads) => {
+                    let block_size =
+                        parallel_block_size.unwrap_or(infteor::block::DEFAULT_BLOCK_SIZE);
+                    let input = open_input(&input)?;
+                    infteor::compress_blocks(pipeline, block_size, threads, input, &mut output)?
+                }
+                None => compress_input(pipeline, &input, &mut output)?,
+            };
+            output.flush()?;
+        }
+        Commands::Decompress {
+            input,
+            output,
+            to_stdout,
+            no_verify,
+            threads,
+        } => {
+            let output = output_path(&input, output, to_stdout, || {
+                let pipeline = Header::read(&mut File::open(&input)?)?.pipeline;
+                let mut path = input.clone();
+
+                // every stage has added its own extension, the last one outermost
+                for codec in pipeline.stages().iter().rev() {
+                    let extension = Algorithm::of(codec).extension();
+
+                    if path.extension() != Some(OsStr::new(extension)) {
+                        bail!(
+                            "{} does not have the .{extension} extension, name the output with --output",
+                            path.display()
+                        );
+                    }
+
+                    path = path.with_extension("");
+                }
+
+                Ok(path)
+            })?;
+
+            let input = open_input(&input)?;
+            let threads = threads.unwrap_or_else(infteor::block::default_threads);
+
+            replace_output(output.as_deref(), |output| {
+                infteor::decompress_parallel(!no_verify, threads, input, output)?;
+                Ok(())
+            })?;
+        }
+        Commands::Test { inputs } => {
+            let mut failed = 0;
+
+            for input in &inputs {
+                let result = open_input(input)
+                    .map_err(infteor::Error::from)
+                    .and_then(infteor::verify);
+
+                match result {
+                    Ok((header, _)) => {
+                        let stages = header
+                            .pipeline
+                            .stages()
+                            .iter()
+                            .map(Codec::name)
+                            .collect::<Vec<_>>()
+                            .join(" -> ");
+
+                        println!("{}: OK ({stages})", input.display());
+                    }
+                    Err(err) => {
+                        eprintln!("{}: FAILED: {err}", input.display());
+                        failed += 1;
+                    }
+                }
+            }
+
+            if failed > 0 {
+                bail!("{failed} of {} files failed verification", inputs.len());
+            }
+        }
+        Commands::Archive { command } => archive(command)?,
+    }
+
+    Ok(())
+}
+
+fn archive(command: ArchiveCommands) -> Result<()> {
+    match command {
+        ArchiveCommands::Create {
+            archive,
+            inputs,
+            algorithm,
+            then,
+            codec,
         } => {
-            let output = File::create(output.unwrap_or(input.clone().with_added_extension("lz78")))?;
-            let input = File::open(input)?;
+            let output = BufWriter::new(File::create(&archive)?);
+            let mut writer =
+                ArchiveWriter::new(output, codec.pipeline(algorithm, &then))?.exclude(&archive)?;
 
-            lz78::encode(input, output, dictionary_size)?;
+            for input in &inputs {
+                writer.add_path(input)?;
+            }
+
+            writer.finish()?.flush()?;
         }
-        Commands::Decompress { input, output } => {
-            let output = File::create(output.unwrap_or(input.clone().with_extension("")))?;
-            let input = File::open(input)?;
+        ArchiveCommands::List { archive } => {
+            let reader = ArchiveReader::new(BufReader::new(File::open(archive)?))?;
 
-            lz78::decode(input, output)?;
+            for entry in reader.entries() {
+                println!("{}", list_line(entry));
+            }
+        }
+        ArchiveCommands::Extract {
+            archive,
+            directory,
+            no_verify,
+        } => {
+            let mut reader = ArchiveReader::new(BufReader::new(File::open(archive)?))?;
+            reader.extract(&directory, !no_verify)?;
         }
     }
 
     Ok(())
 }
 
+/// Mode, sizes, modification time, pipeline and path of an entry, folders
+/// end with `/`.
+fn list_line(entry: &Entry) -> String {
+    let time = format_time(entry.mtime);
+
+    match &entry.kind {
+        EntryKind::File {
+            size,
+            pipeline,
+            compressed_size,
+            ..
+        } => {
+            let stages = pipeline
+                .stages()
+                .iter()
+                .map(Codec::name)
+                .collect::<Vec<_>>()
+                .join(" -> ");
+
+            format!(
+                "{:04o} {size:>12} {compressed_size:>12} {time} {}  ({stages})",
+                entry.mode, entry.path
+            )
+        }
+        EntryKind::Directory => {
+            format!(
+                "{:04o} {:>12} {:>12} {time} {}/",
+                entry.mode, "-", "-", entry.path
+            )
+        }
+    }
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time.
+fn format_time(seconds: i64) -> String {
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+
+    // civil date from days since the epoch, by Howard Hinnant
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        time / 3600,
+        time % 3600 / 60
+    )
+}
+
+/// Path standing for stdin as input and for stdout as output, as in gzip.
+const STD_STREAM: &str = "-";
+
+fn is_std_stream(path: &Path) -> bool {
+    path.as_os_str() == STD_STREAM
+}
+
+/// Where the output goes, `None` for stdout. Output of stdin goes to stdout
+/// unless given, output of a file defaults to a path derived from it.
+fn output_path(
+    input: &Path,
+    output: Option<PathBuf>,
+    to_stdout: bool,
+    default: impl FnOnce() -> Result<PathBuf>,
+) -> Result<Option<PathBuf>> {
+    match output {
+        _ if to_stdout => Ok(None),
+        Some(output) => Ok((!is_std_stream(&output)).then_some(output)),
+        None if is_std_stream(input) => Ok(None),
+        None => default().map(Some),
+    }
+}
+
+fn open_input(path: &Path) -> io::Result<Box<dyn Read>> {
+    match is_std_stream(path) {
+        true => Ok(Box::new(io::stdin().lock())),
+        false => Ok(Box::new(File::open(path)?)),
+    }
+}
+
+/// Largest input read into memory for pipelines reading it twice, when it
+/// comes from stdin or another stream that can't be read again.
+const MAX_BUFFERED_INPUT: u64 = 1 << 30;
+
+/// Compresses `path` as it is read. Regular files are read again by pipelines
+/// reading their input twice, such as static Huffman, which keep other input
+/// in memory instead, up to [`MAX_BUFFERED_INPUT`] bytes.
+fn compress_input(pipeline: Pipeline, path: &Path, output: impl Write) -> Result<Trailer> {
+    let stream: Box<dyn Read> = match is_std_stream(path) {
+        true => Box::new(io::stdin().lock()),
+        false => {
+            let file = File::open(path)?;
+
+            if file.metadata()?.is_file() {
+                return Ok(infteor::compress_seekable(pipeline, file, output)?);
+            }
+
+            Box::new(file)
+        }
+    };
+
+    if !pipeline.reads_input_twice() {
+        return Ok(infteor::compress(pipeline, stream, output)?);
+    }
+
+    if is_std_stream(path) && io::stdin().is_terminal() {
+        bail!(
+            "{} needs its whole input before compressing it, which is not typed in a terminal, \
+            redirect stdin or give a file",
+            pipeline.stages()[0].name()
+        );
+    }
+
+    let stream = BufferLimit {
+        inner: stream,
+        remaining: MAX_BUFFERED_INPUT,
+    };
+
+    Ok(infteor::compress(pipeline, stream, output)?)
+}
+
+/// Fails reading past `remaining` bytes, rather than keeping a stream of any
+/// size in memory.
+struct BufferLimit<R> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R: Read> Read for BufferLimit<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // one byte more shows whether the stream goes on past the limit
+        let max = buf.len().min(self.remaining.saturating_add(1) as usize);
+        let len = self.inner.read(&mut buf[..max])?;
+
+        if len as u64 > self.remaining {
+            return Err(io::Error::other(format!(
+                "input is larger than {} MiB, which is kept in memory for codecs reading it \
+                twice unless it is a regular file; compress a file or use --threads",
+                MAX_BUFFERED_INPUT >> 20
+            )));
+        }
+
+        self.remaining -= len as u64;
+
+        Ok(len)
+    }
+}
+
+fn create_output(path: Option<&Path>) -> io::Result<BufWriter<Box<dyn Write>>> {
+    let output: Box<dyn Write> = match path {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    Ok(BufWriter::new(output))
+}
+
+/// Writes output with `write` to a temporary file next to `path`, renamed to
+/// `path` only once it succeeds, so a failure leaves no partial file behind.
+/// Stdout, devices and other special files are written to directly.
+fn replace_output(
+    path: Option<&Path>,
+    write: impl FnOnce(&mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    let path = match path {
+        Some(path) if fs::metadata(path).map_or(true, |metadata| metadata.is_file()) => path,
+        path => {
+            let mut output = create_output(path)?;
+            write(&mut output)?;
+            return Ok(output.flush()?);
+        }
+    };
+
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or(OsStr::new("output")));
+    name.push(format!(".{}.tmp", process::id()));
+    let temporary = path.with_file_name(name);
+
+    let mut output = BufWriter::new(File::create_new(&temporary)?);
+    let result = write(&mut output)
+        .and_then(|_| {
+            Ok(output
+                .into_inner()
+                .map_err(io::IntoInnerError::into_error)?)
+        })
+        .and_then(|_| Ok(fs::rename(&temporary, path)?));
+
+    if result.is_err() {
+        // the error at hand matters more than a leftover file
+        let _ = fs::remove_file(&temporary);
+    }
+
+    result
+}
+
 fn main() {
-    run().unwrap();
+    if let Err(err) = run() {
+        eprintln!("error: {err:#}");
+        process::exit(1);
+    }
 }
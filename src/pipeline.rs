@@ -0,0 +1,292 @@
+//! Uniform interface over the codecs of all crates, and chains of them.
+
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+
+use crate::{
+    container::Codec,
+    delta,
+    error::{Error, Result},
+};
+
+/// Input of an encoder, some codecs read it twice: to gather statistics first
+/// and to code it afterwards.
+pub trait Input: Read + Seek {}
+
+impl<T: Read + Seek> Input for T {}
+
+pub trait Encoder {
+    fn encode(&self, input: &mut dyn Input, output: &mut dyn Write) -> Result<()>;
+
+    /// Encodes `input` into a new buffer, without a container header.
+    fn encode_to_vec(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        self.encode(&mut Cursor::new(input), &mut output)?;
+
+        Ok(output)
+    }
+}
+
+pub trait Decoder {
+    fn decode(&self, input: &mut dyn Read, output: &mut dyn Write) -> Result<()>;
+
+    /// Decodes `input` into a new buffer, it must not have a container header.
+    fn decode_to_vec(&self, mut input: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        self.decode(&mut input, &mut output)?;
+
+        Ok(output)
+    }
+}
+
+impl Codec {
+    /// Codec gathers statistics of its whole input before coding it, reading
+    /// the input twice.
+    pub fn reads_input_twice(&self) -> bool {
+        matches!(
+            self,
+            Codec::Huffman(_) | Codec::Range { .. } | Codec::Tans { .. }
+        )
+    }
+}
+
+impl Encoder for Codec {
+    fn encode(&self, input: &mut dyn Input, output: &mut dyn Write) -> Result<()> {
+        match *self {
+            Codec::Huffman(options) => huffman::compress_with(options, input, output)?,
+            Codec::Lz78 { strategy, format } => lz78::encode_raw(input, output, strategy, format)?,
+            Codec::HuffmanStream {
+                options,
+                block_size,
+            } => huffman::compress_stream(options, block_size as usize, input, output)?,
+            Codec::AdaptiveHuffman { word_size } => {
+                huffman::compress_adaptive(word_size, input, output)?
+            }
+            Codec::Lzw { strategy } => lz78::lzw::encode_raw(input, output, strategy)?,
+            Codec::Lz77(options) => lz77::encode(input, output, options)?,
+            Codec::Range { word_size } => entropy::range::compress(word_size, input, output)?,
+            Codec::AdaptiveRange { word_size } => {
+                entropy::range::compress_adaptive(word_size, input, output)?
+            }
+            Codec::Tans { word_size } => entropy::tans::compress(word_size, input, output)?,
+            Codec::AdaptiveRans { word_size } => {
+                entropy::rans::compress_adaptive(word_size, input, output)?
+            }
+            Codec::Bwt { block_size } => bwt::compress(block_size, input, output)?,
+            Codec::Delta => delta::encode(input, output)?,
+        }
+
+        Ok(())
+    }
+}
+
+impl Decoder for Codec {
+    fn decode(&self, input: &mut dyn Read, output: &mut dyn Write) -> Result<()> {
+        match *self {
+            Codec::Huffman(options) => {
+                huffman::decompress_with(options.tree_format, input, output)?
+            }
+            Codec::Lz78 { strategy, format } => lz78::decode_raw(strategy, format, input, output)?,
+            Codec::HuffmanStream { options, .. } => {
+                huffman::decompress_stream(options.tree_format, input, output)?
+            }
+            Codec::AdaptiveHuffman { .. } => huffman::decompress_adaptive(input, output)?,
+            Codec::Lzw { strategy } => lz78::lzw::decode_raw(strategy, input, output)?,
+            Codec::Lz77(options) => lz77::decode(options, input, output)?,
+            Codec::Range { .. } => entropy::range::decompress(input, output)?,
+            Codec::AdaptiveRange { .. } => entropy::range::decompress_adaptive(input, output)?,
+            Codec::Tans { .. } => entropy::tans::decompress(input, output)?,
+            Codec::AdaptiveRans { .. } => entropy::rans::decompress_adaptive(input, output)?,
+            Codec::Bwt { .. } => bwt::decompress(input, output)?,
+            Codec::Delta => delta::decode(input, output)?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Codecs applied one after another, each to the output of the previous one.
+/// Output of every stage but the last is kept in memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pipeline {
+    stages: Vec<Codec>,
+}
+
+impl Pipeline {
+    /// Largest number of stages, their count is stored in a single byte.
+    pub const MAX_STAGES: usize = u8::MAX as usize;
+
+    pub fn new(first: Codec) -> Self {
+        Self {
+            stages: vec![first],
+        }
+    }
+
+    /// Appends `codec`, it is applied to the output of all the previous stages.
+    pub fn then(mut self, codec: Codec) -> Self {
+        self.stages.push(codec);
+        self
+    }
+
+    pub fn from_stages(stages: Vec<Codec>) -> Result<Self> {
+        if !(1..=Self::MAX_STAGES).contains(&stages.len()) {
+            return Err(Error::InvalidStageCount(stages.len()));
+        }
+
+        Ok(Self { stages })
+    }
+
+    pub fn stages(&self) -> &[Codec] {
+        &self.stages
+    }
+
+    /// Pipeline reads its input twice, which depends on the first stage only:
+    /// later ones read the output of the previous stage, in memory anyway.
+    pub fn reads_input_twice(&self) -> bool {
+        self.stages[0].reads_input_twice()
+    }
+
+    /// Same as [`Encoder::encode`], for input that can only be read once. It
+    /// is read into memory first if the pipeline reads it twice.
+    pub fn encode_stream(&self, input: &mut dyn Read, output: &mut dyn Write) -> Result<()> {
+        if self.reads_input_twice() {
+            let mut buffer = Vec::new();
+            input.read_to_end(&mut buffer)?;
+
+            return self.encode(&mut Cursor::new(buffer), output);
+        }
+
+        self.encode(&mut Unseekable(input), output)
+    }
+}
+
+/// Input of pipelines reading it once, from start to end.
+struct Unseekable<R>(R);
+
+impl<R: Read> Read for Unseekable<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<R> Seek for Unseekable<R> {
+    fn seek(&mut self, _: SeekFrom) -> io::Result<u64> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+impl From<Codec> for Pipeline {
+    fn from(codec: Codec) -> Self {
+        Self::new(codec)
+    }
+}
+
+impl Encoder for Pipeline {
+    fn encode(&self, input: &mut dyn Input, output: &mut dyn Write) -> Result<()> {
+        let (last, stages) = self.stages.split_last().unwrap();
+        // output of the previous stage, none before the first one
+        let mut buffer = None;
+
+        for stage in stages {
+            let mut stage_output = Vec::new();
+
+            match &buffer {
+                None => stage.encode(input, &mut stage_output)?,
+                Some(buffer) => stage.encode(&mut Cursor::new(buffer), &mut stage_output)?,
+            }
+
+            buffer = Some(stage_output);
+        }
+
+        match &buffer {
+            None => last.encode(input, output),
+            Some(buffer) => last.encode(&mut Cursor::new(buffer), output),
+        }
+    }
+}
+
+impl Decoder for Pipeline {
+    fn decode(&self, input: &mut dyn Read, output: &mut dyn Write) -> Result<()> {
+        let (first, stages) = self.stages.split_first().unwrap();
+        let mut buffer: Option<Vec<u8>> = None;
+
+        for stage in stages.iter().rev() {
+            let mut stage_output = Vec::new();
+
+            match &buffer {
+                None => stage.decode(input, &mut stage_output)?,
+                Some(buffer) => stage.decode(&mut &buffer[..], &mut stage_output)?,
+            }
+
+            buffer = Some(stage_output);
+        }
+
+        match &buffer {
+            None => first.decode(input, output),
+            Some(buffer) => first.decode(&mut &buffer[..], output),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use lz78::dictionary::PruningStrategy;
+
+    use super::{Decoder, Encoder, Pipeline};
+    use crate::{container::Codec, error::Error};
+
+    fn roundtrip(pipeline: &Pipeline, data: &[u8]) -> usize {
+        let compressed = pipeline.encode_to_vec(data).unwrap();
+        assert_eq!(pipeline.decode_to_vec(&compressed).unwrap(), data);
+
+        compressed.len()
+    }
+
+    #[test]
+    fn roundtrip_chained_stages() {
+        let data = b"abracadabra abracadabra abracadabra".repeat(8);
+
+        for pipeline in [
+            Pipeline::new(Codec::Lz78 {
+                strategy: PruningStrategy::Never,
+                format: lz78::TokenFormat::Gamma,
+            })
+            .then(Codec::Huffman(huffman::Options::new(8))),
+            Pipeline::new(Codec::Delta).then(Codec::Lzw {
+                strategy: PruningStrategy::Never,
+            }),
+            Pipeline::new(Codec::Bwt { block_size: 100 })
+                .then(Codec::Delta)
+                .then(Codec::AdaptiveRans { word_size: 8 }),
+        ] {
+            roundtrip(&pipeline, &data);
+            roundtrip(&pipeline, &[]);
+        }
+    }
+
+    #[test]
+    fn delta_should_help_on_ramps() {
+        let data = (0..4096u32).map(|i| (i * 3) as u8).collect::<Vec<_>>();
+        let lz78 = Codec::Lz78 {
+            strategy: PruningStrategy::Never,
+            format: lz78::TokenFormat::Gamma,
+        };
+
+        let plain = roundtrip(&Pipeline::new(lz78), &data);
+        let delta = roundtrip(&Pipeline::new(Codec::Delta).then(lz78), &data);
+
+        assert!(delta * 4 < plain);
+    }
+
+    #[test]
+    fn should_limit_stage_count() {
+        assert!(matches!(
+            Pipeline::from_stages(vec![]),
+            Err(Error::InvalidStageCount(0))
+        ));
+        assert!(matches!(
+            Pipeline::from_stages(vec![Codec::Delta; 256]),
+            Err(Error::InvalidStageCount(256))
+        ));
+        assert!(Pipeline::from_stages(vec![Codec::Delta; 255]).is_ok());
+    }
+}